@@ -2,9 +2,43 @@ use yaxi::ewmh::EwmhWindowType;
 use serde::{Serialize, Deserialize};
 use clap::{Parser, Subcommand, Args, ValueEnum};
 
+use std::path::PathBuf;
+
 const DOCK: [EwmhWindowType; 3] = [EwmhWindowType::Dock, EwmhWindowType::Toolbar, EwmhWindowType::Menu];
 const FLOAT: [EwmhWindowType; 3] = [EwmhWindowType::Splash, EwmhWindowType::Utility, EwmhWindowType::Dialog];
 
+/// has to be bumped whenever anything sent over the socket changes shape, bincode doesn't carry
+/// any field or variant names so a mismatched client would otherwise be decoded as garbage
+pub const PROTOCOL_VERSION: u32 = 6;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Header {
+    pub protocol: u32,
+    pub version: String,
+}
+
+impl Header {
+    pub fn new() -> Header {
+        Header {
+            protocol: PROTOCOL_VERSION,
+            version: VERSION.to_string(),
+        }
+    }
+
+    pub fn is_compatible(&self) -> bool {
+        self.protocol == PROTOCOL_VERSION && self.version == VERSION
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Response {
+    Ok,
+    Message(String),
+    Error(String),
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Subcommand, Serialize, Deserialize)]
 pub enum State {
@@ -43,6 +77,95 @@ pub enum Direction {
     East,
 }
 
+/// what directional focus does once there is no window left in the direction
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum WrapMode {
+    Stop,
+
+    /// continues on the opposite side of the same screen
+    Wrap,
+
+    /// continues on the next screen in the direction
+    Monitor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum SnapPos {
+    Center,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum Pos {
+    First,
+    Last,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum Layer {
+    Tiled,
+    Floating,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Subcommand, Serialize, Deserialize)]
+pub enum LayoutMode {
+    /// the windows are laid out by the tree
+    Tiled,
+
+    /// the first windows share the master area and the rest are stacked next to it, the tree is
+    /// only used for the order of the windows
+    MasterStack {
+        #[arg(short = 'f', long, default_value_t = 55)]
+        master_factor: i8,
+
+        #[arg(short = 'c', long, default_value_t = 1)]
+        master_count: usize,
+    },
+
+    /// the windows fill a grid row by row, a last row that isn't full is stretched to the width
+    Grid {
+        #[arg(short, long, default_value_t = 2)]
+        columns: usize,
+    },
+}
+
+/// the events a hook can run on, the hook gets the window or desktop as its first argument
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum HookEvent {
+    Desktop,
+    Add,
+    Remove,
+    Focus,
+}
+
+/// what happens when a window is sent to a desktop past the last one
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum Overflow {
+    /// the window goes to the last desktop instead
+    Clamp,
+
+    /// the next desktop is added to every screen, an index past that one is rejected
+    Create,
+
+    /// the command fails and the error is returned to the client
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum InsertMode {
+    Manual,
+
+    /// splits wide windows side by side and tall windows on top of each other
+    Automatic,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Subcommand, Serialize, Deserialize)]
 pub enum Change {
     Add {
@@ -59,6 +182,23 @@ pub enum Change {
     },
 }
 
+/// a change of the gaps in pixels, which go past what fits a ratio
+#[derive(Debug, Clone, Copy, PartialEq, Subcommand, Serialize, Deserialize)]
+pub enum GapChange {
+    Add {
+        #[arg(value_name = "VALUE")]
+        value: u8,
+    },
+    Sub {
+        #[arg(value_name = "VALUE")]
+        value: u8,
+    },
+    Set {
+        #[arg(value_name = "VALUE")]
+        value: u8,
+    },
+}
+
 #[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
 pub enum NodeCommand {
     Insert {
@@ -70,6 +210,11 @@ pub enum NodeCommand {
 
         #[arg(short, long)]
         toggle: bool,
+
+        /// splits the selected window for the next mapped window instead of whatever is focused
+        /// by then, the preselection is drawn on it and automatic insert mode fits its area
+        #[arg(short, long)]
+        anchor: bool,
     },
     State {
         #[command(subcommand)]
@@ -93,10 +238,131 @@ pub enum NodeCommand {
         #[command(subcommand)]
         change: Change,
     },
+    Snap {
+        #[arg(value_enum)]
+        position: SnapPos,
+    },
+    Resize {
+        #[command(subcommand)]
+        dir: Direction,
+
+        #[arg(short, long, allow_hyphen_values = true)]
+        px: i16,
+    },
 
+    /// moves the nearest split of both axes by `ratio` percent so the window grows on every
+    /// side at once, the neighbours on both axes give way
+    Expand {
+        #[arg(short, long, allow_hyphen_values = true)]
+        ratio: i8,
+    },
+    Contract {
+        #[arg(short, long, allow_hyphen_values = true)]
+        ratio: i8,
+    },
+
+    Kill {
+        #[arg(short, long)]
+        force: bool,
+    },
+    MoveInContainer {
+        #[arg(value_enum)]
+        pos: Pos,
+    },
+    Mark {
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+    Unmark {
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+
+    Equalize,
     Reverse,
+    ToggleSplit,
+
+    /// moves the window to the front of the order, which is the master slot in master/stack
+    Promote,
+
+    /// rebuilds the selected split as a single row or column of equally sized windows
+    Flatten {
+        #[command(subcommand)]
+        dir: Direction,
+    },
+
+    /// stores the shape of the tree on the focused desktop in `~/.config/yokai/layouts/<name>.json`
+    SaveLayout {
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+
+    /// rebuilds the focused desktop in a saved shape, the windows fill the slots in order and the
+    /// slots left over become receptacles that the next windows are put into
+    LoadLayout {
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+
+    /// removes the receptacles of the focused desktop, the windows take over their space
+    ClearReceptacles,
+
+    /// marks the window to be moved by the next drop, moving the focus elsewhere forgets it
+    Grab,
+
+    /// moves the grabbed window next to the selected one on the `dir` side
+    Drop {
+        #[command(subcommand)]
+        dir: Direction,
+    },
+
+    /// keeps new windows from splitting the window, a neighbour is split instead
+    Private {
+        #[arg(short, long)]
+        toggle: bool,
+    },
     Close,
-    Kill,
+    Pin,
+    Unpin,
+    FocusLayer {
+        #[arg(value_name = "LAYER")]
+        layer: Layer,
+    },
+
+    /// moves the split that ratio, equalize and reverse operate on one level up or down, the
+    /// level goes back to the parent of the focused window whenever the focus changes
+    Parent,
+    Child,
+
+    /// moves the pointer to the center of the window, nothing happens if it isn't visible
+    Warp,
+
+    /// starts another instance of the program behind the window, it is inserted like any new window
+    Duplicate,
+
+    /// centers a floating window on its screen, tiled windows are only floated first with `--float`
+    Center {
+        #[arg(short, long)]
+        float: bool,
+    },
+
+    /// focuses the nearest visible window in the direction
+    FocusDirection {
+        #[command(subcommand)]
+        dir: Direction,
+    },
+
+    /// focuses the most recently used window whose title contains the query, ignoring case
+    FocusByTitle {
+        #[arg(value_name = "QUERY")]
+        query: String,
+    },
+
+    /// rotates the windows through the slots of the tree, the shape stays the same
+    Cycle {
+        #[arg(short, long)]
+        reverse: bool,
+    },
 }
 
 #[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
@@ -105,6 +371,12 @@ pub enum DesktopCommand {
         #[arg(short, long)]
         desktop: usize,
     },
+
+    /// swaps a desktop into the current slot of the focused screen
+    Pull {
+        #[arg(short, long)]
+        desktop: usize,
+    },
 }
 
 #[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
@@ -115,11 +387,38 @@ pub enum ConfigCommand {
 
         #[arg(short, long)]
         pinned: bool,
+
+        #[arg(short, long)]
+        wrap: bool,
     },
     Window {
         #[arg(short, long)]
         gaps: u8,
     },
+
+    /// stores the gaps, padding and border width under a name, whatever is left out stays as it
+    /// is when the preset is applied
+    DefinePreset {
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        #[arg(short, long)]
+        gaps: Option<u8>,
+
+        #[arg(short, long, num_args = 4, value_names = ["TOP", "BOTTOM", "LEFT", "RIGHT"])]
+        padding: Option<Vec<u16>>,
+
+        #[arg(short, long)]
+        border_width: Option<u16>,
+    },
+    Preset {
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+    GapsChange {
+        #[command(subcommand)]
+        change: GapChange,
+    },
     Border {
         #[arg(short, long)]
         normal: String,
@@ -129,6 +428,15 @@ pub enum ConfigCommand {
 
         #[arg(short, long)]
         width: u16,
+
+        #[arg(long)]
+        floating_normal: Option<String>,
+
+        #[arg(long)]
+        floating_focused: Option<String>,
+
+        #[arg(long)]
+        floating_width: Option<u16>,
     },
     Padding {
         #[arg(short, long)]
@@ -143,9 +451,193 @@ pub enum ConfigCommand {
         #[arg(short, long)]
         right: u16,
     },
+    MonitorPadding {
+        #[arg(short, long)]
+        monitor: usize,
+
+        #[arg(short, long)]
+        top: u16,
+
+        #[arg(short, long)]
+        bottom: u16,
+
+        #[arg(short, long)]
+        left: u16,
+
+        #[arg(short, long)]
+        right: u16,
+    },
+    Mirror {
+        #[arg(short, long)]
+        source: usize,
+
+        #[arg(short, long)]
+        target: usize,
+    },
+    Borders {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    FocusOnMap {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    FocusedBorderOnly {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// overrides the state picked from the window type, docks are always left alone
+    DefaultState {
+        #[command(subcommand)]
+        state: Option<State>,
+    },
+    /// lets a tiled terminal be replaced by the windows of programs started from it
+    Swallow {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+
+        /// the classes or instances of the terminals that may swallow, the list is kept if omitted
+        #[arg(long = "terminal", value_name = "CLASS")]
+        terminals: Vec<String>,
+    },
+    DesktopOverflow {
+        #[arg(value_name = "POLICY")]
+        policy: Overflow,
+    },
+    /// only raises the windows whose position in the stacking order changed when retiling,
+    /// which avoids flickering shadows under a compositor
+    LazyRestack {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// the smallest and largest percentage a split ratio can have
+    RatioBounds {
+        #[arg(value_name = "MIN")]
+        min: i8,
+
+        #[arg(value_name = "MAX")]
+        max: i8,
+    },
+    /// runs a shell command on every event of the kind, leaving out the command clears the hooks
+    Hook {
+        #[arg(value_name = "EVENT")]
+        event: HookEvent,
+
+        #[arg(value_name = "COMMAND")]
+        command: Option<String>,
+    },
+    /// stops publishing the desktops and the active window for pagers and taskbars
+    Ewmh {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+
+    /// exits on the first error instead of logging it and handling the next event
+    Strict {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    DragToRetile {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    InsertMode {
+        #[arg(value_name = "MODE")]
+        mode: InsertMode,
+    },
+    Layout {
+        #[command(subcommand)]
+        mode: LayoutMode,
+    },
+    Presel {
+        #[arg(value_name = "COLOR")]
+        color: String,
+    },
+    Mouse {
+        #[arg(short, long)]
+        modifier: String,
+
+        #[arg(long)]
+        move_button: u8,
+
+        #[arg(long)]
+        resize_button: u8,
+    },
 
+    /// toggles both pointer warps at once
     PointerFollowsFocus,
+
+    /// warps the pointer when a node command moves the focus
+    PointerWarpOnFocusChange {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+
+    /// warps the pointer when a desktop command switches the desktop
+    PointerWarpOnSwitch {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+
     FocusFollowsPointer,
+    IgnoreMotifHints,
+
+    /// new windows and desktop switches go to the monitor the pointer last entered instead of the
+    /// one holding the focus
+    PointerMonitor {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+
+    /// leaving out the mode goes back to wrapping onto other monitors with several screens and
+    /// stopping with a single one
+    FocusWrap {
+        #[arg(value_name = "MODE")]
+        mode: Option<WrapMode>,
+    },
+
+    /// waits until the pointer rested on a window for this long before focus follows it
+    FocusDelay {
+        #[arg(value_name = "MS")]
+        delay: u64,
+    },
+
+    /// whether windows asking for fullscreen through `_NET_WM_STATE` get to cover their monitor
+    HonorFullscreen {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+
+    /// interpolates ratio changes over the given duration instead of jumping to the new ratio
+    Animate {
+        #[arg(value_name = "ENABLED", action = clap::ArgAction::Set)]
+        enabled: bool,
+
+        #[arg(value_name = "MS", default_value_t = 150)]
+        duration_ms: u16,
+    },
+}
+
+#[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
+pub enum WmCommand {
+    /// the version of the running window manager, `--version` prints the one of the client
+    Version,
+}
+
+#[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
+pub enum QueryCommand {
+    Tree,
+
+    /// the tree of the focused desktop drawn as an indented diagram
+    Ascii,
+
+    /// the geometry, current desktop and desktop names of every monitor as json, monitors are
+    /// named by their index
+    Monitors,
+
+    /// a `bspc wm -g` style status line, monitors are named by index and only the tiled layout
+    /// is ever reported since there is no monocle layout
+    Report,
 }
 
 #[derive(Debug, Clone, ValueEnum, Serialize, Deserialize)]
@@ -167,6 +659,7 @@ pub enum Descriptor {
     Focused,
     Biggest,
     Smallest,
+    Marked,
 }
 
 #[derive(Debug, Clone, ValueEnum, Serialize, Deserialize)]
@@ -185,13 +678,20 @@ pub enum Modifier {
 #[derive(Debug, Clone, Args, Serialize, Deserialize)]
 pub struct Selector {
     #[arg(value_enum)]
-    descriptor: Descriptor,
+    pub descriptor: Descriptor,
 
     #[arg(value_enum)]
-    modifier: Modifier,
+    pub modifier: Modifier,
 
     #[arg(long, short)]
-    path: Vec<Jump>,
+    pub path: Vec<Jump>,
+
+    #[arg(long)]
+    pub mark: Option<String>,
+
+    /// applies the command to every matching window instead of just one
+    #[arg(long)]
+    pub all: bool,
 }
 
 #[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
@@ -210,11 +710,47 @@ pub enum Command {
     #[command(subcommand)]
     Config(ConfigCommand),
 
+    #[command(subcommand)]
+    Query(QueryCommand),
+
+    #[command(subcommand)]
+    Wm(WmCommand),
+
+    Dump {
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+    },
+
+    Subscribe {
+        #[arg(short, long)]
+        follow: bool,
+    },
+
+    /// focuses the most recently used window with a matching `WM_CLASS` or runs the program
+    RunOrRaise {
+        #[arg(short, long)]
+        class: String,
+
+        #[arg(short, long)]
+        program: String,
+    },
+
+    /// stops managing new windows and retiling until thawed, the windows mapped in the meantime
+    /// are managed on thaw
+    Freeze,
+    Thaw,
+
+    Sync,
     Exit,
 }
 
 #[derive(Debug, Parser, Serialize, Deserialize)]
+#[command(version)]
 pub struct Arguments {
+    /// runs the command on the screen with this index instead of the focused one
+    #[arg(long, global = true)]
+    pub target_monitor: Option<usize>,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -1,9 +1,12 @@
 use serde::{Serialize, Deserialize};
 
 use std::os::unix::net::UnixStream;
-use std::io::Write;
+use std::net::Shutdown;
+use std::io::{self, BufRead, BufReader, Write};
 use std::env;
 
+use ipc::{Header, Response};
+
 
 pub struct Client {
     stream: UnixStream,
@@ -18,14 +21,41 @@ impl Client {
         })
     }
 
-    pub fn send<T>(&mut self, object: T) -> Result<(), Box<dyn std::error::Error>>
+    pub fn send<T>(&mut self, object: T) -> Result<Response, Box<dyn std::error::Error>>
     where
         T: Serialize + for<'a> Deserialize<'a>
     {
-        let bytes = bincode::serialize(&object)?;
+        let bytes = [bincode::serialize(&Header::new())?, bincode::serialize(&object)?].concat();
+
+        self.stream.write_all(&bytes)?;
+
+        self.stream.shutdown(Shutdown::Write)?;
 
-        self.stream.write_all(&bytes).map_err(|err| err.into())
+        bincode::deserialize_from(&mut self.stream).map_err(|err| err.into())
     }
-}
 
+    /// prints subscribed events as they arrive, stops after the first one unless `follow` is set
+    pub fn listen(&mut self, follow: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let stream = self.stream.try_clone()?;
+
+        // shutting down the socket ends the read loop below so we exit cleanly
+        ctrlc::set_handler(move || {
+            let _ = stream.shutdown(Shutdown::Both);
+        })?;
+
+        let mut stdout = io::stdout().lock();
+
+        for line in BufReader::new(&self.stream).lines() {
+            writeln!(stdout, "{}", line?)?;
+
+            stdout.flush()?;
+
+            if !follow {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
 
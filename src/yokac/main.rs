@@ -1,19 +1,44 @@
 mod client;
 
 use client::Client;
-use ipc::Arguments;
+use ipc::{Arguments, Command, Response};
 
 use clap::Parser;
 
+use std::process;
+use std::path;
+
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Arguments::parse();
+    let mut args = Arguments::parse();
 
-    let mut client = Client::new()?;
+    // the window manager doesn't share our working directory
+    if let Command::Dump { path } = &mut args.command {
+        *path = path::absolute(&path)?;
+    }
 
-    client.send(args)?;
+    let subscribe = match args.command {
+        Command::Subscribe { follow } => Some(follow),
+        _ => None,
+    };
 
-    Ok(())
-}
+    let mut client = Client::new()?;
 
+    match client.send(args)? {
+        Response::Ok => match subscribe {
+            Some(follow) => client.listen(follow),
+            None => Ok(()),
+        },
+        Response::Message(message) => {
+            println!("{message}");
+
+            Ok(())
+        },
+        Response::Error(err) => {
+            eprintln!("yokac: {err}");
+
+            process::exit(1);
+        },
+    }
+}
 
@@ -0,0 +1,65 @@
+use crate::config::Padding;
+
+use std::fs;
+
+
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    pub pid: Option<u32>,
+    pub machine: Option<String>,
+    pub transient_for: Option<u32>,
+    pub instance: Option<String>,
+    pub class: Option<String>,
+    pub borderless: bool,
+
+    /// the client asked to be left out of taskbars and pagers, we still manage it normally
+    pub skip_taskbar: bool,
+    pub skip_pager: bool,
+
+    /// the invisible shadow a client side decorated window draws around itself
+    pub frame: Option<Padding>,
+}
+
+impl Client {
+    /// the pid is only meaningful if the client runs on the same host as us
+    pub fn is_local(&self) -> bool {
+        let hostname = fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+
+        self.machine.as_deref() == Some(hostname.trim())
+    }
+
+    pub fn local_pid(&self) -> Option<u32> {
+        self.pid.filter(|_| self.is_local())
+    }
+
+    /// matches either half of `WM_CLASS`, the same way most rules in other window managers do
+    pub fn matches_class(&self, class: &str) -> bool {
+        self.class.as_deref() == Some(class) || self.instance.as_deref() == Some(class)
+    }
+}
+
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+
+    // the command name can contain spaces so the fields are read after its closing parenthesis
+    stat.rsplit_once(')')?.1
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+/// every process above `pid` up to but not including init
+pub fn ancestors(pid: u32) -> Vec<u32> {
+    let mut ancestors = Vec::new();
+    let mut pid = pid;
+
+    while let Some(parent) = parent_pid(pid).filter(|parent| *parent > 1) {
+        ancestors.push(parent);
+
+        pid = parent;
+    }
+
+    ancestors
+}
+
@@ -1,7 +1,13 @@
-use ipc::Direction;
+use serde::Serialize;
 
+use crate::wm::Area;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use std::collections::HashMap;
+
+use ipc::{Direction, HookEvent, InsertMode, LayoutMode, Overflow, State, WrapMode};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub struct Insert {
     pub dir: Direction,
     pub ratio: i8,
@@ -23,28 +29,135 @@ impl Insert {
             ratio,
         }
     }
+
+    /// picks the split direction from the aspect ratio of the area being split
+    pub fn fit(&self, area: Area) -> Insert {
+        match area.width >= area.height {
+            true => Insert::new(Direction::East, self.ratio),
+            false => Insert::new(Direction::South, self.ratio),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Preset {
+    pub gaps: Option<u8>,
+    pub padding: Option<Padding>,
+    pub border_width: Option<u16>,
+}
+
+/// the range every split ratio is kept in, no matter whether it was set, dragged or pinned
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RatioBounds {
+    pub min: i8,
+    pub max: i8,
+}
+
+impl Default for RatioBounds {
+    fn default() -> RatioBounds {
+        RatioBounds {
+            min: 10,
+            max: 90,
+        }
+    }
+}
+
+impl RatioBounds {
+    pub fn new(min: i8, max: i8) -> Result<RatioBounds, Box<dyn std::error::Error>> {
+        if !(1..=99).contains(&min) || !(1..=99).contains(&max) || min >= max {
+            return Err(format!("invalid ratio bounds {min}..{max}, expected 1 <= min < max <= 99").into());
+        }
+
+        Ok(RatioBounds {
+            min,
+            max,
+        })
+    }
+
+    pub fn clamp(&self, ratio: i32) -> i8 {
+        ratio.clamp(self.min as i32, self.max as i32) as i8
+    }
+
+    /// rejects a ratio asked for by a client instead of quietly clamping it
+    pub fn check(&self, ratio: i8) -> Result<i8, Box<dyn std::error::Error>> {
+        match self.clamp(ratio as i32) == ratio {
+            true => Ok(ratio),
+            false => Err(format!("ratio {ratio} is outside of {}..={}", self.min, self.max).into()),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PointerFocus {
     pub focus_follows: bool,
-    pub pointer_follows: bool,
+
+    /// warps the pointer to windows focused by a node command, e.g. from a key binding
+    pub warp_on_focus: bool,
+
+    /// warps the pointer to the focus after switching desktops, or to the center of an empty one
+    pub warp_on_switch: bool,
+
+    /// how long the pointer has to rest on a window before it is focused, in milliseconds
+    pub delay: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Desktops {
     pub names: Vec<String>,
     pub pinned: bool,
+    pub wrap: bool,
+}
+
+impl Desktops {
+    /// maps a requested desktop onto one of `length` desktops by either wrapping or clamping
+    pub fn resolve(&self, desktop: usize, length: usize) -> usize {
+        match length {
+            0 => 0,
+            _ if self.wrap => desktop % length,
+            _ => desktop.min(length - 1),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct Border {
     pub normal: u32,
     pub focused: u32,
     pub width: u16,
+    pub floating_normal: Option<u32>,
+    pub floating_focused: Option<u32>,
+    pub floating_width: Option<u16>,
+    pub enabled: bool,
+    pub focused_only: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Border {
+    pub fn pixel(&self, focused: bool, floating: bool) -> u32 {
+        match (focused, floating) {
+            (true, true) => self.floating_focused.unwrap_or(self.focused),
+            (true, false) => self.focused,
+            (false, true) => self.floating_normal.unwrap_or(self.normal),
+            (false, false) => self.normal,
+        }
+    }
+
+    pub fn width(&self, floating: bool) -> u16 {
+        match self.enabled {
+            true => floating.then_some(self.floating_width).flatten().unwrap_or(self.width),
+            false => 0,
+        }
+    }
+
+    /// the width a tiled window has to make room for
+    pub fn tiled(&self, focused: bool) -> u16 {
+        match self.focused_only && !focused {
+            true => 0,
+            false => self.width(false),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub struct Padding {
     pub top: u16,
     pub bottom: u16,
@@ -52,13 +165,76 @@ pub struct Padding {
     pub right: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+pub struct Mouse {
+    pub modifier: u16,
+    pub move_button: u8,
+    pub resize_button: u8,
+}
+
+impl Mouse {
+    /// button 0 doesn't exist and a button can't both move and resize
+    pub fn new(modifier: &str, move_button: u8, resize_button: u8) -> Result<Mouse, Box<dyn std::error::Error>> {
+        if move_button == 0 || resize_button == 0 || move_button == resize_button {
+            return Err(format!("invalid mouse buttons {move_button} and {resize_button}, expected two different buttons of at least 1").into());
+        }
+
+        Ok(Mouse {
+            modifier: Mouse::parse_modifier(modifier)?,
+            move_button,
+            resize_button,
+        })
+    }
+
+    pub fn parse_modifier(modifier: &str) -> Result<u16, Box<dyn std::error::Error>> {
+        match modifier.to_lowercase().as_str() {
+            "shift" => Ok(1 << 0),
+            "lock" => Ok(1 << 1),
+            "control" | "ctrl" => Ok(1 << 2),
+            "mod1" | "alt" => Ok(1 << 3),
+            "mod2" => Ok(1 << 4),
+            "mod3" => Ok(1 << 5),
+            "mod4" | "super" => Ok(1 << 6),
+            "mod5" => Ok(1 << 7),
+            _ => Err(format!("unknown modifier: {modifier}").into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Configuration {
     pub insert: Insert,
+    pub insert_mode: InsertMode,
+    pub layout: LayoutMode,
+    pub default_state: Option<State>,
     pub pf: PointerFocus,
     pub desktops: Desktops,
     pub border: Border,
     pub padding: Padding,
+    pub mouse: Mouse,
+    pub mirror: Option<(usize, usize)>,
+    pub ignore_motif: bool,
+    pub focus_on_map: bool,
+    pub drag_to_retile: bool,
+    pub strict: bool,
+    pub swallow: bool,
+
+    /// the classes or instances of windows that count as terminals when swallowing
+    pub terminals: Vec<String>,
+    pub ewmh: bool,
+    pub lazy_restack: bool,
+    pub pointer_monitor: bool,
+    pub focus_wrap: Option<WrapMode>,
+
+    /// duration of ratio animations in milliseconds, ratios change instantly when this is none
+    pub animate: Option<u16>,
+
+    pub honor_fullscreen: bool,
+    pub hooks: Vec<(HookEvent, String)>,
+    pub bounds: RatioBounds,
+    pub presets: HashMap<String, Preset>,
+    pub overflow: Overflow,
+    pub presel: u32,
     pub gaps: u8,
 }
 
@@ -66,18 +242,29 @@ impl Configuration {
     pub fn new() -> Configuration {
         Configuration {
             insert: Insert::default(),
+            insert_mode: InsertMode::Manual,
+            layout: LayoutMode::Tiled,
+            default_state: None,
             pf: PointerFocus {
                 focus_follows: false,
-                pointer_follows: false,
+                warp_on_focus: false,
+                warp_on_switch: false,
+                delay: 0,
             },
             desktops: Desktops {
                 names: Vec::new(),
                 pinned: false,
+                wrap: false,
             },
             border: Border {
                 normal: 0x000000ff,
                 focused: 0xffffffff,
                 width: 1,
+                floating_normal: None,
+                floating_focused: None,
+                floating_width: None,
+                enabled: true,
+                focused_only: false,
             },
             padding: Padding {
                 top: 0,
@@ -85,9 +272,156 @@ impl Configuration {
                 left: 0,
                 right: 0,
             },
+            mouse: Mouse {
+                modifier: 1 << 6,
+                move_button: 1,
+                resize_button: 3,
+            },
+            mirror: None,
+            ignore_motif: false,
+            focus_on_map: true,
+            drag_to_retile: false,
+            strict: false,
+            swallow: false,
+            terminals: ["st", "st-256color", "xterm", "urxvt", "alacritty", "kitty", "foot", "wezterm", "konsole", "xfce4-terminal", "gnome-terminal"]
+                .iter()
+                .map(|terminal| terminal.to_string())
+                .collect(),
+            ewmh: true,
+            lazy_restack: false,
+            pointer_monitor: false,
+            focus_wrap: None,
+            animate: None,
+            honor_fullscreen: true,
+            hooks: Vec::new(),
+            bounds: RatioBounds::default(),
+            presets: HashMap::new(),
+            overflow: Overflow::Reject,
+            presel: 0x4c7899ff,
             gaps: 0,
         }
     }
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_ratio_outside_of_the_bounds_is_rejected() {
+        let bounds = RatioBounds::default();
+
+        assert!(bounds.check(-5).is_err());
+        assert!(bounds.check(0).is_err());
+        assert!(bounds.check(i8::MAX).is_err());
+
+        assert_eq!(bounds.check(bounds.min).ok(), Some(bounds.min));
+        assert_eq!(bounds.check(50).ok(), Some(50));
+        assert_eq!(bounds.check(bounds.max).ok(), Some(bounds.max));
+    }
+
+    fn desktops(wrap: bool) -> Desktops {
+        Desktops {
+            names: Vec::new(),
+            pinned: false,
+            wrap,
+        }
+    }
+
+    #[test]
+    fn resolve_clamps_to_the_last_desktop() {
+        let desktops = desktops(false);
+
+        assert_eq!(desktops.resolve(0, 4), 0);
+        assert_eq!(desktops.resolve(3, 4), 3);
+        assert_eq!(desktops.resolve(4, 4), 3);
+        assert_eq!(desktops.resolve(usize::MAX, 4), 3);
+    }
+
+    #[test]
+    fn resolve_wraps_around() {
+        let desktops = desktops(true);
+
+        assert_eq!(desktops.resolve(0, 4), 0);
+        assert_eq!(desktops.resolve(3, 4), 3);
+        assert_eq!(desktops.resolve(4, 4), 0);
+        assert_eq!(desktops.resolve(9, 4), 1);
+    }
+
+    #[test]
+    fn resolve_without_desktops_is_the_first_one() {
+        assert_eq!(desktops(false).resolve(0, 0), 0);
+        assert_eq!(desktops(false).resolve(2, 0), 0);
+        assert_eq!(desktops(true).resolve(2, 0), 0);
+    }
+
+    #[test]
+    fn ratio_bounds_have_to_be_ordered_and_in_range() {
+        assert_eq!(RatioBounds::new(20, 80).ok(), Some(RatioBounds { min: 20, max: 80 }));
+        assert!(RatioBounds::new(1, 99).is_ok());
+
+        assert!(RatioBounds::new(80, 20).is_err());
+        assert!(RatioBounds::new(50, 50).is_err());
+        assert!(RatioBounds::new(0, 50).is_err());
+        assert!(RatioBounds::new(50, 100).is_err());
+        assert!(RatioBounds::new(-10, 50).is_err());
+    }
+
+    #[test]
+    fn ratio_bounds_clamp_out_of_range_ratios() {
+        let bounds = RatioBounds::default();
+
+        assert_eq!(bounds.clamp(50), 50);
+        assert_eq!(bounds.clamp(bounds.min as i32), bounds.min);
+        assert_eq!(bounds.clamp(-120), bounds.min);
+        assert_eq!(bounds.clamp(0), bounds.min);
+        assert_eq!(bounds.clamp(300), bounds.max);
+        assert_eq!(bounds.clamp(i32::MAX), bounds.max);
+    }
+
+    #[test]
+    fn fit_splits_along_the_longer_side() {
+        let insert = Insert::new(Direction::West, 40);
+
+        assert_eq!(insert.fit(Area::new(0, 0, 1920, 1080)), Insert::new(Direction::East, 40));
+        assert_eq!(insert.fit(Area::new(0, 0, 600, 1080)), Insert::new(Direction::South, 40));
+
+        // a square area is split side by side
+        assert_eq!(insert.fit(Area::new(0, 0, 800, 800)), Insert::new(Direction::East, 40));
+    }
+
+    #[test]
+    fn parse_modifier_knows_the_x_modifiers_and_their_aliases() {
+        assert_eq!(Mouse::parse_modifier("shift").ok(), Some(1 << 0));
+        assert_eq!(Mouse::parse_modifier("ctrl").ok(), Mouse::parse_modifier("control").ok());
+        assert_eq!(Mouse::parse_modifier("alt").ok(), Some(1 << 3));
+        assert_eq!(Mouse::parse_modifier("super").ok(), Mouse::parse_modifier("mod4").ok());
+        assert_eq!(Mouse::parse_modifier("mod5").ok(), Some(1 << 7));
+    }
+
+    #[test]
+    fn mouse_buttons_have_to_exist_and_differ() {
+        assert!(Mouse::new("super", 1, 3).is_ok());
+
+        assert!(Mouse::new("super", 0, 3).is_err());
+        assert!(Mouse::new("super", 1, 0).is_err());
+        assert!(Mouse::new("super", 2, 2).is_err());
+        assert!(Mouse::new("hyper", 1, 3).is_err());
+    }
+
+    #[test]
+    fn parse_modifier_ignores_case() {
+        assert_eq!(Mouse::parse_modifier("Super").ok(), Some(1 << 6));
+        assert_eq!(Mouse::parse_modifier("MOD1").ok(), Some(1 << 3));
+    }
+
+    #[test]
+    fn parse_modifier_rejects_unknown_names() {
+        assert!(Mouse::parse_modifier("hyper").is_err());
+        assert!(Mouse::parse_modifier("mod6").is_err());
+        assert!(Mouse::parse_modifier("").is_err());
+        assert!(Mouse::parse_modifier("super+shift").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+/// desktops are numbered screen by screen, so with two screens of three desktops the second
+/// screen holds the global desktops 3 to 5, `sizes` holds the desktop count of every screen
+pub fn global_index(sizes: &[usize], screen: usize, local: usize) -> usize {
+    sizes.iter().take(screen).sum::<usize>() + local
+}
+
+/// the screen and the local desktop a global index refers to
+pub fn local_index(sizes: &[usize], global: usize) -> Option<(usize, usize)> {
+    let mut first = 0;
+
+    for (screen, size) in sizes.iter().enumerate() {
+        if global < first + size {
+            return Some((screen, global - first));
+        }
+
+        first += size;
+    }
+
+    None
+}
+
+/// the index in `desired` from which every window has to be raised to get from the `current`
+/// stacking order to the desired one, both go from the bottom to the top
+pub fn restack_from(current: &[u32], desired: &[u32]) -> usize {
+    current.iter()
+        .zip(desired)
+        .take_while(|(current, desired)| current == desired)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global_index_of_the_first_and_last_screen() {
+        let sizes = [3, 2, 4];
+
+        assert_eq!(global_index(&sizes, 0, 0), 0);
+        assert_eq!(global_index(&sizes, 0, 2), 2);
+        assert_eq!(global_index(&sizes, 1, 0), 3);
+        assert_eq!(global_index(&sizes, 2, 0), 5);
+        assert_eq!(global_index(&sizes, 2, 3), 8);
+    }
+
+    #[test]
+    fn local_index_of_the_first_and_last_screen() {
+        let sizes = [3, 2, 4];
+
+        assert_eq!(local_index(&sizes, 0), Some((0, 0)));
+        assert_eq!(local_index(&sizes, 2), Some((0, 2)));
+        assert_eq!(local_index(&sizes, 3), Some((1, 0)));
+        assert_eq!(local_index(&sizes, 8), Some((2, 3)));
+        assert_eq!(local_index(&sizes, 9), None);
+    }
+
+    #[test]
+    fn local_index_skips_screens_without_desktops() {
+        assert_eq!(local_index(&[2, 0, 1], 2), Some((2, 0)));
+        assert_eq!(local_index(&[], 0), None);
+    }
+
+    #[test]
+    fn local_index_undoes_global_index() {
+        let sizes = [1, 4, 2];
+
+        for (screen, size) in sizes.iter().enumerate() {
+            for local in 0..*size {
+                assert_eq!(local_index(&sizes, global_index(&sizes, screen, local)), Some((screen, local)));
+            }
+        }
+    }
+
+    #[test]
+    fn restack_from_the_first_window_out_of_place() {
+        assert_eq!(restack_from(&[1, 2, 3], &[1, 2, 3]), 3);
+        assert_eq!(restack_from(&[1, 2, 3], &[1, 3, 2]), 1);
+        assert_eq!(restack_from(&[1, 2, 3], &[3, 1, 2]), 0);
+
+        // a new window on top only needs itself raised
+        assert_eq!(restack_from(&[1, 2], &[1, 2, 3]), 2);
+    }
+
+    #[test]
+    fn restack_from_an_empty_stack() {
+        assert_eq!(restack_from(&[], &[]), 0);
+        assert_eq!(restack_from(&[], &[1, 2]), 0);
+        assert_eq!(restack_from(&[1, 2], &[]), 0);
+    }
+}
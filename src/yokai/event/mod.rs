@@ -1,9 +1,10 @@
 use yaxi::proto::Event;
 
+use std::sync::mpsc::Sender;
 use std::sync::{Mutex, Condvar};
 use std::collections::VecDeque;
 
-use ipc::Arguments;
+use ipc::{Arguments, Response};
 
 macro_rules! lock {
     ($mutex:expr) => {
@@ -13,7 +14,16 @@ macro_rules! lock {
 
 pub enum EventType {
     XEvent(Event),
-    Config(Arguments),
+    Config(Arguments, Sender<Response>),
+
+    /// the hover delay of the window ran out, it is focused if the pointer is still over it
+    Hover(u32),
+
+    /// the next frame of the animation with this id is due
+    Frame(u64),
+
+    /// the connection to the x server is gone, carries the reason
+    Shutdown(String),
 }
 
 pub struct Queue<T> {
@@ -37,6 +47,10 @@ impl<T> Queue<T> {
         Ok(())
     }
 
+    pub fn is_empty(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(lock!(self.queue)?.is_empty())
+    }
+
     pub fn wait(&self) -> Result<T, Box<dyn std::error::Error>> {
         let mut guard = lock!(self.queue)?;
 
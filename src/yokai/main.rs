@@ -1,15 +1,28 @@
 mod startup;
+mod client;
 mod config;
+mod desktop;
 mod server;
 mod event;
+mod query;
 mod tree;
 mod wm;
 
-use wm::WindowManager;
+use wm::{WindowManager, AlreadyRunning};
+
+use std::process;
 
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut wm = WindowManager::new()?;
+    let mut wm = match WindowManager::new() {
+        Ok(wm) => wm,
+        Err(err) if err.is::<AlreadyRunning>() => {
+            eprintln!("yokai: {err}");
+
+            process::exit(2);
+        },
+        Err(err) => return Err(err),
+    };
 
     wm.run()
 }
@@ -0,0 +1,99 @@
+use crate::config::Configuration;
+use crate::client::Client;
+use crate::tree::{Node, Split};
+use crate::wm::Area;
+
+use serde::Serialize;
+
+use std::collections::HashMap;
+
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TreeNode {
+    Leaf {
+        window: u32,
+        depth: usize,
+        pid: Option<u32>,
+        skip_taskbar: bool,
+        skip_pager: bool,
+    },
+    Internal {
+        split: Split,
+        ratio: i8,
+        left: Box<TreeNode>,
+        right: Box<TreeNode>,
+    },
+    Receptacle,
+}
+
+impl TreeNode {
+    pub fn new(node: &Node, root: &Node, clients: &HashMap<u32, Client>) -> TreeNode {
+        match node {
+            Node::Leaf { window, .. } => TreeNode::Leaf {
+                window: window.id(),
+                depth: root.depth(window.id()).unwrap_or_default(),
+                pid: clients.get(&window.id()).and_then(|client| client.pid),
+                skip_taskbar: clients.get(&window.id()).map(|client| client.skip_taskbar).unwrap_or(false),
+                skip_pager: clients.get(&window.id()).map(|client| client.skip_pager).unwrap_or(false),
+            },
+            Node::Internal { left, right, split, ratio } => TreeNode::Internal {
+                split: *split,
+                ratio: *ratio,
+                left: Box::new(TreeNode::new(left, root, clients)),
+                right: Box::new(TreeNode::new(right, root, clients)),
+            },
+            Node::Receptacle => TreeNode::Receptacle,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DesktopQuery {
+    pub screen: usize,
+    pub desktop: usize,
+    pub focus: Option<u32>,
+    pub tree: Option<TreeNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WindowInfo {
+    pub id: u32,
+    pub title: String,
+    pub pid: Option<u32>,
+    pub skip_taskbar: bool,
+    pub skip_pager: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonitorQuery {
+    pub name: String,
+    pub area: Area,
+    pub current: usize,
+    pub desktops: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DesktopDump {
+    pub name: Option<String>,
+    pub tree: Option<TreeNode>,
+    pub floating: Vec<u32>,
+    pub last_focus: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScreenDump {
+    pub area: Area,
+    pub current: usize,
+    pub desktops: Vec<DesktopDump>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Dump {
+    pub version: &'static str,
+    pub focus: Option<u32>,
+    pub screens: Vec<ScreenDump>,
+    pub windows: Vec<WindowInfo>,
+    pub config: Configuration,
+}
+
@@ -1,23 +1,70 @@
 use crate::event::{Queue, EventType};
 
-use std::os::unix::net::UnixListener;
-use std::sync::Arc;
-use std::io::Read;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::io::{Read, Write};
 use std::thread;
 use std::env;
 use std::fs;
 
-use ipc::Arguments;
+use ipc::{Arguments, Command, Header, Response, PROTOCOL_VERSION, VERSION};
 
 
+pub struct Subscribers {
+    streams: Mutex<Vec<UnixStream>>,
+}
+
+impl Subscribers {
+    pub fn new() -> Subscribers {
+        Subscribers {
+            streams: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// the stream is made nonblocking so a subscriber that stops reading can't stall the window
+    /// manager, once its buffer is full the write fails and it is dropped
+    pub fn add(&self, stream: UnixStream) -> Result<(), Box<dyn std::error::Error>> {
+        stream.set_nonblocking(true)?;
+
+        self.streams.lock().map_err(|_| "failed to lock")?.push(stream);
+
+        Ok(())
+    }
+
+    /// writes a line to every subscriber, subscribers that have gone away or fell behind are dropped
+    pub fn emit(&self, line: impl AsRef<str>) -> Result<(), Box<dyn std::error::Error>> {
+        self.streams.lock()
+            .map_err(|_| "failed to lock")?
+            .retain_mut(|stream| writeln!(stream, "{}", line.as_ref()).is_ok());
+
+        Ok(())
+    }
+}
+
 pub struct Server {
     listener: UnixListener,
     events: Arc<Queue<EventType>>,
+    subscribers: Arc<Subscribers>,
+}
+
+fn path() -> Result<String, Box<dyn std::error::Error>> {
+    Ok(format!("{}/.config/yokai/ipc", env::var("HOME")?))
+}
+
+/// removes the socket so clients fail to connect instead of hanging on a dead window manager
+pub fn cleanup() -> Result<(), Box<dyn std::error::Error>> {
+    let path = path()?;
+
+    if fs::exists(&path)? {
+        fs::remove_file(&path)?;
+    }
+
+    Ok(())
 }
 
 impl Server {
-    pub fn new(events: Arc<Queue<EventType>>) -> Result<Server, Box<dyn std::error::Error>> {
-        let path = format!("{}/.config/yokai/ipc", env::var("HOME")?);
+    pub fn new(events: Arc<Queue<EventType>>, subscribers: Arc<Subscribers>) -> Result<Server, Box<dyn std::error::Error>> {
+        let path = path()?;
 
         if fs::exists(&path)? {
             fs::remove_file(&path)?;
@@ -26,30 +73,122 @@ impl Server {
         Ok(Server {
             listener: UnixListener::bind(path)?,
             events,
+            subscribers,
         })
     }
 
     pub fn listen(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         for stream in self.listener.incoming() {
+            let mut stream = stream?;
             let mut buffer: Vec<u8> = Vec::new();
 
-            stream?.read_to_end(&mut buffer)?;
+            stream.read_to_end(&mut buffer)?;
+
+            match decode(&buffer) {
+                Ok(Arguments { command: Command::Subscribe { .. }, .. }) => {
+                    respond(&mut stream, Response::Ok)?;
+
+                    self.subscribers.add(stream)?;
+                },
+                Ok(args) => {
+                    let (sender, receiver) = mpsc::channel();
 
-            let args: Arguments = bincode::deserialize(&buffer)?;
+                    self.events.push(EventType::Config(args, sender))?;
 
-            self.events.push(EventType::Config(args))?;
+                    let response = receiver.recv()
+                        .unwrap_or_else(|_| Response::Error(String::from("window manager stopped before responding")));
+
+                    respond(&mut stream, response)?;
+                },
+                Err(err) => respond(&mut stream, Response::Error(err.to_string()))?,
+            }
         }
 
         Ok(())
     }
 }
 
-pub fn spawn(events: Arc<Queue<EventType>>) {
+/// decodes straight from the buffer so a corrupt length prefix fails instead of allocating whatever it claims
+fn decode(buffer: &[u8]) -> Result<Arguments, Box<dyn std::error::Error>> {
+    let header: Header = bincode::deserialize(buffer)
+        .map_err(|_| format!("malformed message header, expected protocol {PROTOCOL_VERSION} ({VERSION})"))?;
+
+    if !header.is_compatible() {
+        return Err(format!(
+            "client version {} (protocol {}) does not match server version {VERSION} (protocol {PROTOCOL_VERSION})",
+            header.version,
+            header.protocol,
+        ).into());
+    }
+
+    let offset = bincode::serialized_size(&header)? as usize;
+
+    bincode::deserialize(&buffer[offset..]).map_err(|err| err.into())
+}
+
+fn respond(stream: &mut UnixStream, response: Response) -> Result<(), Box<dyn std::error::Error>> {
+    // the client may have gone away without waiting for a response
+    let _ = stream.write_all(&bincode::serialize(&response)?);
+
+    Ok(())
+}
+
+pub fn spawn(events: Arc<Queue<EventType>>, subscribers: Arc<Subscribers>) {
     thread::spawn(move || {
-        if let Ok(mut server) = Server::new(events) {
+        if let Ok(mut server) = Server::new(events, subscribers) {
             let _ = server.listen();
         }
     });
 }
 
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscribe() -> Arguments {
+        Arguments {
+            target_monitor: None,
+            command: Command::Subscribe { follow: true },
+        }
+    }
+
+    #[test]
+    fn decode_reads_the_arguments_after_the_header() {
+        let mut buffer = bincode::serialize(&Header::new()).unwrap();
+
+        buffer.extend(bincode::serialize(&subscribe()).unwrap());
+
+        assert!(matches!(decode(&buffer), Ok(Arguments { command: Command::Subscribe { follow: true }, .. })));
+    }
+
+    #[test]
+    fn decode_rejects_a_payload_without_a_header() {
+        let buffer = bincode::serialize(&subscribe()).unwrap();
+
+        assert!(decode(&buffer).is_err());
+    }
+
+    #[test]
+    fn decode_rejects_a_length_prefix_longer_than_the_message() {
+        let mut buffer = bincode::serialize(&PROTOCOL_VERSION).unwrap();
+
+        buffer.extend(bincode::serialize(&u64::MAX).unwrap());
+
+        assert!(decode(&buffer).is_err());
+    }
+
+    #[test]
+    fn decode_rejects_a_mismatched_protocol() {
+        let header = Header {
+            protocol: PROTOCOL_VERSION + 1,
+            version: VERSION.to_string(),
+        };
+
+        let mut buffer = bincode::serialize(&header).unwrap();
+
+        buffer.extend(bincode::serialize(&subscribe()).unwrap());
+
+        assert!(decode(&buffer).is_err());
+    }
+}
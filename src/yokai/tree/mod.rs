@@ -1,99 +1,425 @@
-use crate::config::{Insert, Padding};
+use crate::config::{Insert, Padding, RatioBounds};
 use crate::wm::Area;
 
 use yaxi::window::{Window, WindowKind};
 
-use ipc::Direction;
+use serde::{Serialize, Deserialize};
 
+use std::collections::{HashMap, VecDeque};
+use std::cell::Cell;
 
-pub enum Point {
-    Window(Window),
+use ipc::{Direction, Pos, Jump};
+
+
+/// what the tree has to know about the windows in it, the tests use plain ids since a real window
+/// needs a connection to the server
+pub trait Handle: Clone + PartialEq {
+    fn id(&self) -> u32;
+}
+
+impl Handle for Window {
+    fn id(&self) -> u32 {
+        Window::id(self)
+    }
+}
+
+#[cfg(test)]
+impl Handle for u32 {
+    fn id(&self) -> u32 {
+        *self
+    }
+}
+
+pub enum Point<W = Window> {
+    Window(W),
     Any,
 }
 
+/// the axis a container is divided along, independent of which side a window was inserted on
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Split {
+    /// the children sit side by side
+    Horizontal,
+
+    /// the children sit on top of each other
+    Vertical,
+}
+
+impl Split {
+    pub fn from(dir: Direction) -> Split {
+        match dir {
+            Direction::West | Direction::East => Split::Horizontal,
+            Direction::North | Direction::South => Split::Vertical,
+        }
+    }
+
+    pub fn toggle(self) -> Split {
+        match self {
+            Split::Horizontal => Split::Vertical,
+            Split::Vertical => Split::Horizontal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// the shape of a tree without any windows in it, saved layouts are stored in this shape, e.g.
+/// `{"type": "split", "split": "horizontal", "ratio": 60, "left": {"type": "slot"}, "right": ...}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Template {
+    Slot,
+    Split {
+        left: Box<Template>,
+        right: Box<Template>,
+        split: Split,
+        ratio: i8,
+    },
+}
+
+impl Template {
+    /// fills the slots in order, the slots left over become receptacles
+    pub fn fill<W: Handle>(&self, windows: &mut VecDeque<W>) -> Node<W> {
+        match self {
+            Template::Slot => windows.pop_front().map(Node::root).unwrap_or(Node::Receptacle),
+            Template::Split { left, right, split, ratio } => Node::Internal {
+                left: Box::new(left.fill(windows)),
+                right: Box::new(right.fill(windows)),
+                split: *split,
+                ratio: *ratio,
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
-pub enum Node {
+pub enum Node<W = Window> {
     Leaf {
-        window: Window,
+        window: W,
+
+        /// the area the window was given by the last partition
+        area: Cell<Option<Area>>,
+
+        /// new windows split a neighbour instead of a private leaf
+        private: bool,
     },
     Internal {
-        left: Box<Node>,
-        right: Box<Node>,
-        insert: Insert,
+        left: Box<Node<W>>,
+        right: Box<Node<W>>,
+        split: Split,
+        ratio: i8,
     },
+
+    /// a leaf without a window that keeps its share of the screen empty, the next window that is
+    /// inserted takes it
+    Receptacle,
 }
 
-impl Node {
-    pub fn root(window: Window) -> Node {
-        Node::Leaf { window }
+impl<W: Handle> Node<W> {
+    pub fn root(window: W) -> Node<W> {
+        Node::Leaf {
+            window,
+            area: Cell::new(None),
+            private: false,
+        }
+    }
+
+    /// builds a balanced tree where every window gets an equal share along the split
+    pub fn balanced(windows: &[W], split: Split) -> Option<Node<W>> {
+        match windows {
+            [] => None,
+            [window] => Some(Node::root(window.clone())),
+            _ => {
+                let middle = windows.len() / 2;
+
+                Some(Node::Internal {
+                    left: Box::new(Node::balanced(&windows[..middle], split)?),
+                    right: Box::new(Node::balanced(&windows[middle..], split)?),
+                    split,
+                    ratio: (middle * 100 / windows.len()) as i8,
+                })
+            },
+        }
     }
 
-    pub fn collect(self) -> Vec<Window> {
+    pub fn template(&self) -> Template {
         match self {
-            Node::Leaf { window } => vec![window],
-            Node::Internal { left, right, .. } => {
-                [left.collect(), right.collect()].concat()
+            Node::Leaf { .. } | Node::Receptacle => Template::Slot,
+            Node::Internal { left, right, split, ratio } => Template::Split {
+                left: Box::new(left.template()),
+                right: Box::new(right.template()),
+                split: *split,
+                ratio: *ratio,
             },
         }
     }
 
-    pub fn contains(&self, needle: &Window) -> bool {
+    /// every window in the tree from left to right
+    pub fn leaves(&self) -> Box<dyn Iterator<Item = &W> + '_> {
         match self {
-            Node::Leaf { window } => needle == window,
-            Node::Internal { left, right, .. } => left.contains(needle) || right.contains(needle),
+            Node::Leaf { window, .. } => Box::new(std::iter::once(window)),
+            Node::Internal { left, right, .. } => Box::new(left.leaves().chain(right.leaves())),
+            Node::Receptacle => Box::new(std::iter::empty()),
         }
     }
 
-    pub fn traverse<F>(&self, mut f: F) -> Result<(), Box<dyn std::error::Error>>
-    where
-        F: Clone + Copy + FnMut(&Window) -> Result<(), Box<dyn std::error::Error>>
-    {
+    pub fn leaves_mut(&mut self) -> Box<dyn Iterator<Item = &mut W> + '_> {
         match self {
-            Node::Leaf { window } => f(window),
+            Node::Leaf { window, .. } => Box::new(std::iter::once(window)),
+            Node::Internal { left, right, .. } => Box::new(left.leaves_mut().chain(right.leaves_mut())),
+            Node::Receptacle => Box::new(std::iter::empty()),
+        }
+    }
+
+    /// the leftmost window, `None` while the tree only holds receptacles
+    pub fn first(&self) -> Option<&W> {
+        self.leaves().next()
+    }
+
+    pub fn collect(&self) -> Vec<W> {
+        self.leaves().cloned().collect()
+    }
+
+    /// how many internal nodes sit between the root and the window
+    pub fn depth(&self, needle: u32) -> Option<usize> {
+        match self {
+            Node::Leaf { window, .. } => (window.id() == needle).then_some(0),
             Node::Internal { left, right, .. } => {
-                left.traverse(f)?;
+                left.depth(needle)
+                    .or_else(|| right.depth(needle))
+                    .map(|depth| depth + 1)
+            },
+            Node::Receptacle => None,
+        }
+    }
+
+    /// the sides taken from the root down to the window
+    pub fn path(&self, needle: u32) -> Option<Vec<Side>> {
+        match self {
+            Node::Leaf { window, .. } => (window.id() == needle).then(Vec::new),
+            Node::Internal { left, right, .. } => {
+                let (side, mut path) = left.path(needle)
+                    .map(|path| (Side::Left, path))
+                    .or_else(|| right.path(needle).map(|path| (Side::Right, path)))?;
 
-                right.traverse(f)
+                path.insert(0, side);
+
+                Some(path)
             },
+            Node::Receptacle => None,
         }
     }
 
-    pub fn partition(&self, area: Area, gaps: u8) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn walk(&self, path: &[Side]) -> Option<&Node<W>> {
+        match (self, path.split_first()) {
+            (_, None) => Some(self),
+            (Node::Internal { left, .. }, Some((Side::Left, rest))) => left.walk(rest),
+            (Node::Internal { right, .. }, Some((Side::Right, rest))) => right.walk(rest),
+            (Node::Leaf { .. } | Node::Receptacle, Some(_)) => None,
+        }
+    }
+
+    /// applies the jumps to the path of the window, `None` if a jump leaves the tree
+    pub fn jump(&self, needle: u32, jumps: &[Jump]) -> Option<&Node<W>> {
+        let mut path = self.path(needle)?;
+
+        for jump in jumps {
+            match jump {
+                Jump::Parent => {
+                    path.pop()?;
+                },
+                Jump::Brother => {
+                    let side = path.last_mut()?;
+
+                    *side = match side {
+                        Side::Left => Side::Right,
+                        Side::Right => Side::Left,
+                    };
+                },
+                Jump::First => path.push(Side::Left),
+                Jump::Second => path.push(Side::Right),
+            }
+
+            self.walk(&path)?;
+        }
+
+        self.walk(&path)
+    }
+
+    pub fn contains(&self, needle: &W) -> bool {
+        self.leaves().any(|window| window == needle)
+    }
+
+    fn cache(&self, needle: u32) -> Option<&Cell<Option<Area>>> {
         match self {
-            Node::Leaf { window } => {
-                window.mov_resize(
-                    area.x + gaps as u16,
-                    area.y + gaps as u16,
-                    area.width - (gaps as u16 * 2),
-                    area.height - (gaps as u16 * 2),
-                )?;
+            Node::Leaf { window, area, .. } => (window.id() == needle).then_some(area),
+            Node::Internal { left, right, .. } => left.cache(needle).or_else(|| right.cache(needle)),
+            Node::Receptacle => None,
+        }
+    }
 
-                window.map(WindowKind::Window)?;
+    /// the area of the window as of the last partition, this saves a `get_geometry` round trip
+    pub fn cached_area(&self, needle: u32) -> Option<Area> {
+        self.cache(needle)?.get()
+    }
+
+    /// computes the area of every leaf without touching the windows
+    pub fn layout(&self, area: Area, gaps: u8) -> Vec<(W, Area)> {
+        match self {
+            Node::Leaf { window, .. } => vec![(window.clone(), area.inset(gaps as u16))],
+            Node::Internal { left, right, split, ratio } => {
+                let (first, second) = Node::split(area, *split, *ratio);
+
+                [left.layout(first, gaps), right.layout(second, gaps)].concat()
             },
-            Node::Internal { left, right, insert } => {
-                let factor = insert.ratio.min(100) as f64 / 100.0;
+            Node::Receptacle => Vec::new(),
+        }
+    }
+
+    /// adjusts the ratio of every split next to a pinned leaf so the leaf keeps its size, when both
+    /// sides of a split are pinned the split is over-constrained and stays proportional
+    pub fn constrain(&mut self, area: Area, pinned: &HashMap<u32, (u16, u16)>, bounds: RatioBounds) {
+        if let Node::Internal { left, right, split, ratio } = self {
+            let pin = |node: &Node<W>| match node {
+                Node::Leaf { window, .. } => pinned.get(&window.id()).copied(),
+                Node::Internal { .. } | Node::Receptacle => None,
+            };
 
-                match insert.dir {
-                    Direction::North | Direction::South => {
-                        left.partition(Area::new(area.x, area.y, area.width, (area.height as f64 * factor) as u16), gaps)?;
+            let extent = match split {
+                Split::Horizontal => area.width,
+                Split::Vertical => area.height,
+            } as i32;
 
-                        right.partition(Area::new(area.x, area.y + (area.height as f64 * factor) as u16, area.width, area.height - (area.height as f64 * factor) as u16), gaps)?;
-                    },
-                    Direction::West | Direction::East => {
-                        left.partition(Area::new(area.x, area.y, (area.width as f64 * factor) as u16, area.height), gaps)?;
+            let size = |(width, height): (u16, u16)| match split {
+                Split::Horizontal => width,
+                Split::Vertical => height,
+            } as i32;
 
-                        right.partition(Area::new(area.x + (area.width as f64 * factor) as u16, area.y, area.width - (area.width as f64 * factor) as u16, area.height), gaps)?;
-                    },
+            let constrained = match (pin(left), pin(right)) {
+                (Some(pinned), None) => Some(size(pinned) * 100 / extent.max(1)),
+                (None, Some(pinned)) => Some(100 - size(pinned) * 100 / extent.max(1)),
+                _ => None,
+            };
+
+            if let Some(constrained) = constrained {
+                *ratio = bounds.clamp(constrained);
+            }
+
+            let (first, second) = Node::split(area, *split, *ratio);
+
+            left.constrain(first, pinned, bounds);
+
+            right.constrain(second, pinned, bounds);
+        }
+    }
+
+    /// the windows of the layout with the frame and border they need, leaving out the ones whose
+    /// geometry is the same as the last time it was applied, the cache and `applied` are updated
+    fn changed<'a, F>(&self, layout: &'a [(W, Area)], border: F, frames: &HashMap<u32, Padding>, applied: &mut HashMap<u32, (Area, u16)>) -> Vec<(&'a W, Area, u16)>
+    where
+        F: Fn(&W) -> u16
+    {
+        // windows that left the layout have to be moved again once they come back
+        applied.retain(|wid, _| layout.iter().any(|(window, _)| window.id() == *wid));
+
+        let mut changed = Vec::new();
+
+        for (window, area) in layout.iter() {
+            let border = border(window);
+
+            // the shadow of a client side decorated window hangs over the tile so only the visible
+            // part of the window fills it
+            let frame = frames.get(&window.id())
+                .map(|frame| area.expand(*frame))
+                .unwrap_or(*area);
+
+            if let Some(cache) = self.cache(window.id()) {
+                cache.set(Some(*area));
+            }
+
+            if applied.insert(window.id(), (frame, border)) != Some((frame, border)) {
+                changed.push((window, frame, border));
+            }
+        }
+
+        changed
+    }
+
+    /// moves the nearest split boundary on the `dir` side of the window by `px` pixels,
+    /// returns `None` if the window isn't in this tree and `Some(resized)` otherwise
+    pub fn grow(&mut self, needle: u32, area: Area, dir: Direction, px: i16, bounds: RatioBounds) -> Option<bool> {
+        match self {
+            Node::Leaf { window, .. } => (window.id() == needle).then_some(false),
+            Node::Internal { left, right, split, ratio } => {
+                let (first, second) = Node::split(area, *split, *ratio);
+
+                let (resized, in_left) = match left.grow(needle, first, dir, px, bounds) {
+                    Some(resized) => (resized, true),
+                    None => (right.grow(needle, second, dir, px, bounds)?, false),
+                };
+
+                if resized {
+                    return Some(true);
                 }
+
+                let (extent, sign) = match (*split, dir, in_left) {
+                    (Split::Horizontal, Direction::East, true) => (area.width, 1),
+                    (Split::Horizontal, Direction::West, false) => (area.width, -1),
+                    (Split::Vertical, Direction::South, true) => (area.height, 1),
+                    (Split::Vertical, Direction::North, false) => (area.height, -1),
+                    _ => return Some(false),
+                };
+
+                // rounded so small steps on wide splits still move the boundary by at least a percent
+                let delta = match ((px as f64 * 100.0) / (extent as f64).max(1.0)).round() as i32 {
+                    0 => (px as i32).signum(),
+                    delta => delta,
+                };
+
+                *ratio = bounds.clamp(*ratio as i32 + delta * sign);
+
+                Some(true)
             },
+            Node::Receptacle => None,
         }
+    }
 
-        Ok(())
+    /// moves the nearest split of each axis above the window by `delta` percent in its favour,
+    /// returns whether the window was found
+    pub fn expand(&mut self, needle: u32, delta: i8, bounds: RatioBounds) -> bool {
+        self.zoom(needle, delta, bounds).is_some()
+    }
+
+    /// the axes whose nearest split below this node was already moved
+    fn zoom(&mut self, needle: u32, delta: i8, bounds: RatioBounds) -> Option<Vec<Split>> {
+        match self {
+            Node::Leaf { window, .. } => (window.id() == needle).then(Vec::new),
+            Node::Internal { left, right, split, ratio } => {
+                let (mut moved, sign) = match left.zoom(needle, delta, bounds) {
+                    Some(moved) => (moved, 1),
+                    None => (right.zoom(needle, delta, bounds)?, -1),
+                };
+
+                if !moved.contains(split) {
+                    *ratio = bounds.clamp(*ratio as i32 + delta as i32 * sign);
+
+                    moved.push(*split);
+                }
+
+                Some(moved)
+            },
+            Node::Receptacle => None,
+        }
     }
 
     pub fn remove(&mut self, needle: u32) -> bool {
         match self {
-            Node::Leaf { window } => window.id() == needle,
+            Node::Leaf { window, .. } => window.id() == needle,
             Node::Internal { left, right, .. } => {
                 if left.remove(needle) {
                     *self = *right.clone();
@@ -103,28 +429,129 @@ impl Node {
 
                 false
             },
+            Node::Receptacle => false,
         }
     }
 
-    pub fn map_internal<F>(&mut self, needle: u32, f: F) -> bool
+    /// applies `f` to the split `level` splits above the window, a level of zero is its parent,
+    /// returns how far up the search still has to go
+    pub fn map_internal<F>(&mut self, needle: u32, level: usize, f: F) -> Option<usize>
     where
-        F: Clone + Copy + Fn(Box<Node>, Box<Node>, Insert) -> Node
+        F: Clone + Copy + Fn(Box<Node<W>>, Box<Node<W>>, Split, i8) -> Node<W>
     {
         match self {
-            Node::Leaf { window } => window.id() == needle,
-            Node::Internal { left, right, insert } => {
-                if left.map_internal(needle, f) || right.map_internal(needle, f) {
-                    *self = f(left.clone(), right.clone(), *insert);
+            Node::Leaf { window, .. } => (window.id() == needle).then_some(0),
+            Node::Internal { left, right, split, ratio } => {
+                let distance = left.map_internal(needle, level, f)
+                    .or_else(|| right.map_internal(needle, level, f))?;
+
+                if distance == level {
+                    *self = f(left.clone(), right.clone(), *split, *ratio);
+
+                    return None;
+                }
+
+                Some(distance + 1)
+            },
+            Node::Receptacle => None,
+        }
+    }
+
+    /// swaps the window into the first or last slot of its parent split, the ratio is kept
+    pub fn reorder(&mut self, needle: u32, pos: Pos) -> bool {
+        match self {
+            Node::Leaf { window, .. } => window.id() == needle,
+            Node::Internal { left, right, .. } => {
+                let swap = (left.reorder(needle, pos) && pos == Pos::Last)
+                    || (right.reorder(needle, pos) && pos == Pos::First);
+
+                if swap {
+                    std::mem::swap(left, right);
                 }
 
                 false
             },
+            Node::Receptacle => false,
+        }
+    }
+
+    pub fn replace(&mut self, needle: u32, window: W) -> bool {
+        match self.leaves_mut().find(|leaf| leaf.id() == needle) {
+            Some(leaf) => {
+                *leaf = window;
+
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// moves the window to the first slot, the windows in front of it shift back by one
+    pub fn promote(&mut self, needle: u32) -> bool {
+        let mut windows = self.collect();
+
+        match windows.iter().position(|window| window.id() == needle) {
+            Some(index) => {
+                let window = windows.remove(index);
+
+                windows.insert(0, window);
+
+                for (slot, window) in self.leaves_mut().zip(windows) {
+                    *slot = window;
+                }
+
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// moves every window one slot to the right, or to the left when `reverse` is set
+    pub fn rotate(&mut self, reverse: bool) {
+        let mut windows = self.collect();
+
+        match reverse {
+            true => windows.rotate_left(1),
+            false => windows.rotate_right(1),
+        }
+
+        for (slot, window) in self.leaves_mut().zip(windows) {
+            *slot = window;
+        }
+    }
+
+    /// draws the tree with one node per line, leaves are labeled by `label`
+    pub fn render_ascii<F>(&self, label: &F) -> String
+    where
+        F: Fn(&W) -> String
+    {
+        let mut lines = Vec::new();
+
+        self.render_lines(label, String::new(), String::new(), &mut lines);
+
+        lines.join("\n")
+    }
+
+    fn render_lines<F>(&self, label: &F, head: String, tail: String, lines: &mut Vec<String>)
+    where
+        F: Fn(&W) -> String
+    {
+        match self {
+            Node::Leaf { window, .. } => lines.push(format!("{head}{:#x} {}", window.id(), label(window))),
+            Node::Internal { left, right, split, ratio } => {
+                lines.push(format!("{head}{:?} {}%", split, ratio).to_lowercase());
+
+                left.render_lines(label, format!("{tail}├── "), format!("{tail}│   "), lines);
+
+                right.render_lines(label, format!("{tail}└── "), format!("{tail}    "), lines);
+            },
+            Node::Receptacle => lines.push(format!("{head}receptacle")),
         }
     }
 
     pub fn reverse(&mut self) {
         match self {
-            Node::Leaf { .. } => {},
+            Node::Leaf { .. } | Node::Receptacle => {},
             Node::Internal { left, right, .. } => {
                 std::mem::swap(left, right);
 
@@ -135,34 +562,890 @@ impl Node {
         }
     }
 
-    fn find(&mut self, point: &Point) -> Option<&mut Node> {
+    fn find(&mut self, point: &Point<W>) -> Option<&mut Node<W>> {
         match self {
-            Node::Leaf { window } => match point {
+            Node::Leaf { window, .. } => match point {
                 Point::Window(point) => (window == point).then(|| self),
                 Point::Any => Some(self),
             },
             Node::Internal { left, right, .. } => {
                 left.find(&point).or(right.find(&point))
             },
+            Node::Receptacle => None,
+        }
+    }
+
+    fn receptacle(&mut self) -> Option<&mut Node<W>> {
+        match self {
+            Node::Leaf { .. } => None,
+            Node::Internal { left, right, .. } => left.receptacle().or(right.receptacle()),
+            Node::Receptacle => Some(self),
+        }
+    }
+
+    /// drops every receptacle, a split left with a single side collapses into it
+    pub fn prune(self) -> Option<Node<W>> {
+        match self {
+            Node::Leaf { .. } => Some(self),
+            Node::Internal { left, right, split, ratio } => match (left.prune(), right.prune()) {
+                (Some(left), Some(right)) => Some(Node::Internal {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    split,
+                    ratio,
+                }),
+                (left, right) => left.or(right),
+            },
+            Node::Receptacle => None,
+        }
+    }
+
+    /// flips or sets the private flag of the leaf, returns whether the window was found
+    pub fn set_private(&mut self, needle: u32, toggle: bool) -> bool {
+        match self {
+            Node::Leaf { window, private, .. } => {
+                if window.id() == needle {
+                    *private = !(*private && toggle);
+                }
+
+                window.id() == needle
+            },
+            Node::Internal { left, right, .. } => left.set_private(needle, toggle) || right.set_private(needle, toggle),
+            Node::Receptacle => false,
+        }
+    }
+
+    fn privacy(&self) -> Vec<(W, bool)> {
+        match self {
+            Node::Leaf { window, private, .. } => vec![(window.clone(), *private)],
+            Node::Internal { left, right, .. } => [left.privacy(), right.privacy()].concat(),
+            Node::Receptacle => Vec::new(),
+        }
+    }
+
+    /// moves the insertion point off a private leaf onto the closest leaf in order that isn't,
+    /// when every leaf is private the point is left alone
+    fn public_point(&self, point: Point<W>) -> Point<W> {
+        let leaves = self.privacy();
+
+        let index = match &point {
+            Point::Window(target) => leaves.iter().position(|(window, _)| window == target),
+            Point::Any => Some(0),
+        };
+
+        match index.filter(|index| leaves[*index].1) {
+            Some(index) => (1..leaves.len())
+                .flat_map(|distance| [index.checked_sub(distance), Some(index + distance)])
+                .flatten()
+                .filter_map(|index| leaves.get(index))
+                .find(|(_, private)| !private)
+                .map(|(window, _)| Point::Window(window.clone()))
+                .unwrap_or(point),
+            None => point,
+        }
+    }
+
+    /// a point on a window outside of the tree falls back to the leaf of `fallback`, or anywhere
+    /// once that is gone too
+    pub fn tiled_point(&self, point: Point<W>, fallback: Option<u32>) -> Point<W> {
+        match point {
+            Point::Window(target) if !self.contains(&target) => fallback
+                .and_then(|wid| self.leaves().find(|window| window.id() == wid))
+                .cloned()
+                .map(Point::Window)
+                .unwrap_or(Point::Any),
+            point => point,
         }
     }
 
-    pub fn insert(&mut self, window: Window, insert: Insert, point: Point) {
+    pub fn insert(&mut self, window: W, insert: Insert, point: Point<W>) {
+        // a receptacle is filled before anything is split
+        if let Some(node) = self.receptacle() {
+            *node = Node::root(window);
+
+            return;
+        }
+
+        let point = self.public_point(point);
+
         if let Some(node) = self.find(&point) {
+            // the direction only decides which side the new window goes on
             *node = match insert.dir {
                 Direction::East | Direction::South => Node::Internal {
                     left: Box::new(node.clone()),
-                    right: Box::new(Node::Leaf { window }),
-                    insert,
+                    right: Box::new(Node::root(window)),
+                    split: Split::from(insert.dir),
+                    ratio: insert.ratio,
                 },
                 Direction::West | Direction::North => Node::Internal {
-                    left: Box::new(Node::Leaf { window }),
+                    left: Box::new(Node::root(window)),
                     right: Box::new(node.clone()),
-                    insert,
+                    split: Split::from(insert.dir),
+                    ratio: insert.ratio,
                 },
             }
         }
     }
 }
 
+impl Node {
+    pub fn split(area: Area, split: Split, ratio: i8) -> (Area, Area) {
+        match split {
+            Split::Horizontal => area.split_horizontal(ratio),
+            Split::Vertical => area.split_vertical(ratio),
+        }
+    }
+
+    /// moves the windows into a precomputed layout and remembers their areas, windows whose
+    /// geometry is the same as the last time it was applied are left alone
+    pub fn arrange<F>(&self, layout: Vec<(Window, Area)>, border: F, frames: &HashMap<u32, Padding>, applied: &mut HashMap<u32, (Area, u16)>) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: Fn(&Window) -> u16
+    {
+        let changed = self.changed(&layout, border, frames, applied);
+
+        // every window is moved into place before anything is mapped, this way a new window
+        // never shows up on top of a sibling that hasn't been shrunk yet
+        for (window, frame, border) in changed.iter() {
+            let inner = inside_border(*frame, *border);
+
+            window.mov_resize(inner.x, inner.y, inner.width, inner.height)?;
+        }
+
+        for (window, _, _) in changed {
+            window.map(WindowKind::Window)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// the geometry a window is given for the area, the border is drawn outside of the window so it
+/// has to fit inside the area
+pub fn inside_border(area: Area, border: u16) -> Area {
+    Area::new(area.x, area.y, area.width.saturating_sub(border * 2).max(1), area.height.saturating_sub(border * 2).max(1))
+}
+
+/// stacks the windows on top of each other with an equal height each
+fn column<W: Clone>(windows: &[W], area: Area, gaps: u8) -> Vec<(W, Area)> {
+    let height = area.height / windows.len().max(1) as u16;
+
+    windows.iter()
+        .enumerate()
+        .map(|(index, window)| {
+            let y = area.y + height * index as u16;
+
+            // the last window takes whatever the division left over
+            let height = match index + 1 == windows.len() {
+                true => area.y + area.height - y,
+                false => height,
+            };
+
+            (window.clone(), Area::new(area.x, y, area.width, height).inset(gaps as u16))
+        })
+        .collect()
+}
+
+/// puts the windows next to each other with an equal width each
+fn row<W: Clone>(windows: &[W], area: Area, gaps: u8) -> Vec<(W, Area)> {
+    let width = area.width / windows.len().max(1) as u16;
+
+    windows.iter()
+        .enumerate()
+        .map(|(index, window)| {
+            let x = area.x + width * index as u16;
+
+            let width = match index + 1 == windows.len() {
+                true => area.x + area.width - x,
+                false => width,
+            };
+
+            (window.clone(), Area::new(x, area.y, width, area.height).inset(gaps as u16))
+        })
+        .collect()
+}
+
+/// lays the windows out in rows of `columns` windows, the rows share the height equally
+pub fn grid<W: Clone>(windows: &[W], area: Area, columns: usize, gaps: u8) -> Vec<(W, Area)> {
+    let rows = windows.chunks(columns.max(1)).collect::<Vec<&[W]>>();
+    let height = area.height / rows.len().max(1) as u16;
+
+    rows.iter()
+        .enumerate()
+        .flat_map(|(index, windows)| {
+            let y = area.y + height * index as u16;
+
+            let height = match index + 1 == rows.len() {
+                true => area.y + area.height - y,
+                false => height,
+            };
+
+            row(windows, Area::new(area.x, y, area.width, height), gaps)
+        })
+        .collect()
+}
+
+/// the dwm layout, the first `count` windows share the master column which takes `factor` percent
+/// of the width and the remaining windows are stacked in the other column
+pub fn master_stack<W: Clone>(windows: &[W], area: Area, factor: i8, count: usize, gaps: u8) -> Vec<(W, Area)> {
+    let (masters, stack) = windows.split_at(count.min(windows.len()));
+
+    match (masters.is_empty(), stack.is_empty()) {
+        (true, _) => column(stack, area, gaps),
+        (_, true) => column(masters, area, gaps),
+        _ => {
+            let (master, rest) = area.split_horizontal(factor);
+
+            [column(masters, master, gaps), column(stack, rest, gaps)].concat()
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(window: u32) -> Node<u32> {
+        Node::root(window)
+    }
+
+    fn split(left: Node<u32>, right: Node<u32>, split: Split) -> Node<u32> {
+        Node::Internal {
+            left: Box::new(left),
+            right: Box::new(right),
+            split,
+            ratio: 50,
+        }
+    }
+
+    /// `1 | (2 / (3 | 4))`, every window sits one split deeper than the one before it
+    fn nested() -> Node<u32> {
+        split(leaf(1), split(leaf(2), split(leaf(3), leaf(4), Split::Horizontal), Split::Vertical), Split::Horizontal)
+    }
+
+    fn render(node: &Node<u32>) -> String {
+        node.render_ascii(&|_| String::new())
+    }
+
+    fn window(node: Option<&Node<u32>>) -> Option<u32> {
+        match node? {
+            Node::Leaf { window, .. } => Some(*window),
+            Node::Internal { .. } | Node::Receptacle => None,
+        }
+    }
+
+    #[test]
+    fn depth_of_a_single_leaf() {
+        assert_eq!(leaf(1).depth(1), Some(0));
+        assert_eq!(leaf(1).depth(2), None);
+    }
+
+    #[test]
+    fn depth_of_a_nested_tree() {
+        let tree = nested();
+
+        assert_eq!(tree.depth(1), Some(1));
+        assert_eq!(tree.depth(2), Some(2));
+        assert_eq!(tree.depth(4), Some(3));
+        assert_eq!(tree.depth(5), None);
+    }
+
+    #[test]
+    fn jump_from_a_single_leaf() {
+        assert_eq!(window(leaf(1).jump(1, &[])), Some(1));
+
+        assert!(leaf(1).jump(1, &[Jump::Parent]).is_none());
+        assert!(leaf(1).jump(1, &[Jump::Brother]).is_none());
+        assert!(leaf(1).jump(1, &[Jump::First]).is_none());
+    }
+
+    #[test]
+    fn jump_from_a_missing_window() {
+        assert!(nested().jump(5, &[]).is_none());
+        assert!(nested().jump(5, &[Jump::Parent]).is_none());
+    }
+
+    #[test]
+    fn jump_through_a_nested_tree() {
+        let tree = nested();
+
+        assert_eq!(window(tree.jump(4, &[Jump::Brother])), Some(3));
+        assert_eq!(window(tree.jump(4, &[Jump::Parent, Jump::Brother])), Some(2));
+        assert_eq!(window(tree.jump(4, &[Jump::Parent, Jump::Parent, Jump::First])), Some(2));
+        assert_eq!(window(tree.jump(4, &[Jump::Parent, Jump::Parent, Jump::Parent, Jump::First])), Some(1));
+
+        // the root has no parent and a leaf has no children
+        assert!(tree.jump(4, &[Jump::Parent, Jump::Parent, Jump::Parent, Jump::Parent]).is_none());
+        assert!(tree.jump(1, &[Jump::Second]).is_none());
+    }
+
+    #[test]
+    fn rotating_four_slots_four_times_is_a_full_cycle() {
+        let mut tree = nested();
+
+        tree.rotate(false);
+
+        assert_eq!(tree.collect(), vec![4, 1, 2, 3]);
+
+        for _ in 0..3 {
+            tree.rotate(false);
+        }
+
+        assert_eq!(render(&tree), render(&nested()));
+    }
+
+    #[test]
+    fn rotating_backwards_undoes_a_rotation() {
+        let mut tree = nested();
+
+        tree.rotate(true);
+
+        assert_eq!(tree.collect(), vec![2, 3, 4, 1]);
+
+        tree.rotate(false);
+
+        assert_eq!(render(&tree), render(&nested()));
+    }
+
+    #[test]
+    fn rotating_keeps_the_splits_in_place() {
+        let mut tree = nested();
+
+        tree.rotate(false);
+
+        let splits = |node: &Node<u32>| render(node)
+            .lines()
+            .filter(|line| !line.contains("0x"))
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+
+        assert_eq!(splits(&tree), splits(&nested()));
+    }
+
+    #[test]
+    fn toggling_a_split_flips_its_axis() {
+        assert_eq!(Split::Horizontal.toggle(), Split::Vertical);
+        assert_eq!(Split::Vertical.toggle(), Split::Horizontal);
+        assert_eq!(Split::Horizontal.toggle().toggle(), Split::Horizontal);
+    }
+
+    #[test]
+    fn leaves_are_enumerated_in_order() {
+        let tree = split(nested(), split(leaf(5), leaf(6), Split::Vertical), Split::Vertical);
+
+        assert_eq!(tree.leaves().copied().collect::<Vec<u32>>(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(leaf(1).leaves().copied().collect::<Vec<u32>>(), vec![1]);
+
+        assert_eq!(tree.first(), Some(&1));
+        assert!(tree.contains(&6));
+        assert!(!tree.contains(&7));
+    }
+
+    #[test]
+    fn leaves_mut_walk_the_same_order() {
+        let mut tree = nested();
+
+        for (index, window) in tree.leaves_mut().enumerate() {
+            *window = 10 + index as u32;
+        }
+
+        assert_eq!(tree.collect(), vec![10, 11, 12, 13]);
+    }
+
+    fn private(node: &Node<u32>) -> Vec<u32> {
+        node.privacy().into_iter().filter(|(_, private)| *private).map(|(window, _)| window).collect()
+    }
+
+    fn point(point: Point<u32>) -> Option<u32> {
+        match point {
+            Point::Window(window) => Some(window),
+            Point::Any => None,
+        }
+    }
+
+    #[test]
+    fn set_private_toggles_and_sets() {
+        let mut tree = nested();
+
+        assert!(tree.set_private(2, true));
+        assert_eq!(private(&tree), vec![2]);
+
+        assert!(tree.set_private(2, true));
+        assert_eq!(private(&tree), Vec::<u32>::new());
+
+        // without toggle the flag is set no matter what it was
+        assert!(tree.set_private(3, false));
+        assert!(tree.set_private(3, false));
+        assert_eq!(private(&tree), vec![3]);
+
+        assert!(!tree.set_private(5, true));
+    }
+
+    #[test]
+    fn public_point_moves_off_a_private_leaf() {
+        let mut tree = nested();
+
+        tree.set_private(2, false);
+
+        // the closest leaf in order wins, the one before it goes first on a tie
+        assert_eq!(point(tree.public_point(Point::Window(2))), Some(1));
+        assert_eq!(point(tree.public_point(Point::Window(3))), Some(3));
+
+        tree.set_private(1, false);
+
+        assert_eq!(point(tree.public_point(Point::Window(1))), Some(3));
+        assert_eq!(point(tree.public_point(Point::Any)), Some(3));
+    }
+
+    #[test]
+    fn public_point_keeps_the_point_when_every_leaf_is_private() {
+        let mut tree = split(leaf(1), leaf(2), Split::Horizontal);
+
+        tree.set_private(1, false);
+        tree.set_private(2, false);
+
+        assert_eq!(point(tree.public_point(Point::Window(2))), Some(2));
+        assert_eq!(point(tree.public_point(Point::Any)), None);
+    }
+
+    #[test]
+    fn insert_splits_a_public_neighbour_of_a_private_leaf() {
+        let mut tree = split(leaf(1), leaf(2), Split::Horizontal);
+
+        tree.set_private(2, false);
+
+        tree.insert(3, Insert::new(Direction::East, 50), Point::Window(2));
+
+        assert_eq!(tree.collect(), vec![1, 3, 2]);
+        assert_eq!(tree.depth(1), Some(2));
+        assert_eq!(tree.depth(2), Some(1));
+    }
+
+    #[test]
+    fn reorder_swaps_the_window_within_its_parent() {
+        let mut tree = split(leaf(1), leaf(2), Split::Horizontal);
+
+        tree.reorder(1, Pos::Last);
+
+        assert_eq!(tree.collect(), vec![2, 1]);
+
+        tree.reorder(1, Pos::Last);
+
+        assert_eq!(tree.collect(), vec![2, 1]);
+
+        tree.reorder(1, Pos::First);
+
+        assert_eq!(tree.collect(), vec![1, 2]);
+    }
+
+    #[test]
+    fn reorder_leaves_the_rest_of_the_tree_alone() {
+        let mut tree = nested();
+
+        tree.reorder(4, Pos::First);
+
+        assert_eq!(tree.collect(), vec![1, 2, 4, 3]);
+        assert_eq!(tree.depth(4), Some(3));
+
+        tree.reorder(5, Pos::First);
+
+        assert_eq!(tree.collect(), vec![1, 2, 4, 3]);
+
+        let mut single = leaf(1);
+
+        single.reorder(1, Pos::Last);
+
+        assert_eq!(single.collect(), vec![1]);
+    }
+
+    fn areas(layout: Vec<(u32, Area)>) -> Vec<(u16, u16, u16, u16)> {
+        layout.into_iter().map(|(_, area)| (area.x, area.y, area.width, area.height)).collect()
+    }
+
+    #[test]
+    fn grid_of_one_to_five_windows() {
+        let area = Area::new(0, 0, 1200, 900);
+
+        let grid = |count: u32| areas(grid(&(1..=count).collect::<Vec<u32>>(), area, 2, 0));
+
+        assert_eq!(grid(1), vec![(0, 0, 1200, 900)]);
+        assert_eq!(grid(2), vec![(0, 0, 600, 900), (600, 0, 600, 900)]);
+        assert_eq!(grid(3), vec![(0, 0, 600, 450), (600, 0, 600, 450), (0, 450, 1200, 450)]);
+        assert_eq!(grid(4), vec![(0, 0, 600, 450), (600, 0, 600, 450), (0, 450, 600, 450), (600, 450, 600, 450)]);
+        assert_eq!(grid(5), vec![(0, 0, 600, 300), (600, 0, 600, 300), (0, 300, 600, 300), (600, 300, 600, 300), (0, 600, 1200, 300)]);
+    }
+
+    #[test]
+    fn grid_gives_the_remainder_to_the_last_row_and_column() {
+        let layout = areas(grid(&[1, 2, 3, 4, 5, 6, 7], Area::new(10, 20, 1000, 1000), 3, 0));
+
+        assert_eq!(layout[2], (676, 20, 334, 333));
+        assert_eq!(layout[6], (10, 686, 1000, 334));
+    }
+
+    #[test]
+    fn grid_keeps_the_gaps_around_every_window() {
+        let layout = areas(grid(&[1, 2], Area::new(0, 0, 1200, 900), 2, 10));
+
+        assert_eq!(layout, vec![(10, 10, 580, 880), (610, 10, 580, 880)]);
+    }
+
+    #[test]
+    fn master_stack_splits_masters_from_the_stack() {
+        let area = Area::new(0, 0, 1200, 900);
+
+        assert_eq!(areas(master_stack(&[1, 2, 3], area, 60, 1, 0)), vec![(0, 0, 720, 900), (720, 0, 480, 450), (720, 450, 480, 450)]);
+        assert_eq!(areas(master_stack(&[1, 2, 3, 4], area, 50, 2, 0)), vec![(0, 0, 600, 450), (0, 450, 600, 450), (600, 0, 600, 450), (600, 450, 600, 450)]);
+    }
+
+    #[test]
+    fn master_stack_without_a_stack_or_masters_is_one_column() {
+        let area = Area::new(0, 0, 1200, 900);
+
+        assert_eq!(areas(master_stack(&[1, 2], area, 60, 2, 0)), vec![(0, 0, 1200, 450), (0, 450, 1200, 450)]);
+        assert_eq!(areas(master_stack(&[1, 2], area, 60, 5, 0)), vec![(0, 0, 1200, 450), (0, 450, 1200, 450)]);
+        assert_eq!(areas(master_stack(&[1, 2], area, 60, 0, 0)), vec![(0, 0, 1200, 450), (0, 450, 1200, 450)]);
+
+        assert!(master_stack::<u32>(&[], area, 60, 1, 0).is_empty());
+    }
+
+    #[test]
+    fn render_ascii_draws_one_node_per_line() {
+        let expected = [
+            "horizontal 50%",
+            "├── 0x1 w1",
+            "└── vertical 50%",
+            "    ├── 0x2 w2",
+            "    └── horizontal 50%",
+            "        ├── 0x3 w3",
+            "        └── 0x4 w4",
+        ].join("\n");
+
+        assert_eq!(nested().render_ascii(&|window| format!("w{window}")), expected);
+        assert_eq!(leaf(1).render_ascii(&|_| String::from("term")), "0x1 term");
+    }
+
+    fn splits(node: &Node<u32>) -> Vec<Split> {
+        match node {
+            Node::Leaf { .. } | Node::Receptacle => Vec::new(),
+            Node::Internal { left, right, split, .. } => [vec![*split], splits(left), splits(right)].concat(),
+        }
+    }
+
+    fn toggle(left: Box<Node<u32>>, right: Box<Node<u32>>, split: Split, ratio: i8) -> Node<u32> {
+        Node::Internal {
+            left,
+            right,
+            split: split.toggle(),
+            ratio,
+        }
+    }
+
+    #[test]
+    fn map_internal_counts_levels_from_the_parent() {
+        let (horizontal, vertical) = (Split::Horizontal, Split::Vertical);
+
+        let mut tree = nested();
 
+        tree.map_internal(4, 0, toggle);
+
+        assert_eq!(splits(&tree), vec![horizontal, vertical, vertical]);
+
+        let mut tree = nested();
+
+        tree.map_internal(4, 1, toggle);
+
+        assert_eq!(splits(&tree), vec![horizontal, horizontal, horizontal]);
+
+        let mut tree = nested();
+
+        tree.map_internal(4, 2, toggle);
+
+        assert_eq!(splits(&tree), vec![vertical, vertical, horizontal]);
+        assert_eq!(tree.collect(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn map_internal_past_the_root_changes_nothing() {
+        let mut tree = nested();
+
+        assert_eq!(tree.map_internal(4, 3, toggle), Some(3));
+        assert_eq!(tree.map_internal(1, 1, toggle), Some(1));
+        assert_eq!(tree.map_internal(5, 0, toggle), None);
+
+        assert_eq!(splits(&tree), splits(&nested()));
+
+        let mut single = leaf(1);
+
+        assert_eq!(single.map_internal(1, 0, toggle), Some(0));
+    }
+
+    #[test]
+    fn only_windows_with_a_new_geometry_are_changed() {
+        let tree = split(leaf(1), leaf(2), Split::Horizontal);
+        let (frames, mut applied) = (HashMap::new(), HashMap::new());
+
+        let changed = |layout: &[(u32, Area)], border: u16, applied: &mut HashMap<u32, (Area, u16)>| tree.changed(layout, |_| border, &frames, applied)
+            .into_iter()
+            .map(|(window, _, _)| *window)
+            .collect::<Vec<u32>>();
+
+        let layout = tree.layout(Area::new(0, 0, 1000, 500), 0);
+
+        assert_eq!(changed(&layout, 1, &mut applied), vec![1, 2]);
+        assert_eq!(changed(&layout, 1, &mut applied), Vec::<u32>::new());
+
+        // moving the split only touches the windows on either side of it
+        let resized = vec![layout[0].clone(), (2, Area::new(600, 0, 400, 500))];
+
+        assert_eq!(changed(&resized, 1, &mut applied), vec![2]);
+
+        assert_eq!(changed(&resized, 2, &mut applied), vec![1, 2]);
+
+        assert_eq!(tree.cached_area(2), Some(Area::new(600, 0, 400, 500)));
+    }
+
+    #[test]
+    fn windows_that_leave_the_layout_are_changed_when_they_come_back() {
+        let tree = split(leaf(1), leaf(2), Split::Horizontal);
+        let (frames, mut applied) = (HashMap::new(), HashMap::new());
+
+        let layout = tree.layout(Area::new(0, 0, 1000, 500), 0);
+
+        tree.changed(&layout, |_| 0, &frames, &mut applied);
+        tree.changed(&layout[..1], |_| 0, &frames, &mut applied);
+
+        assert!(!applied.contains_key(&2));
+
+        let changed = tree.changed(&layout, |_| 0, &frames, &mut applied);
+
+        assert_eq!(changed.into_iter().map(|(window, _, _)| *window).collect::<Vec<u32>>(), vec![2]);
+    }
+
+    #[test]
+    fn bordered_windows_side_by_side_dont_overlap() {
+        let (border, gaps) = (4, 0);
+
+        let tree = split(leaf(1), leaf(2), Split::Horizontal);
+
+        let layout = tree.layout(Area::new(0, 0, 1000, 500), gaps);
+
+        let outer = tree.changed(&layout, |_| border, &HashMap::new(), &mut HashMap::new())
+            .into_iter()
+            .map(|(_, frame, border)| {
+                let inner = inside_border(frame, border);
+
+                (inner.x, inner.width + border * 2, inner.height + border * 2)
+            })
+            .collect::<Vec<(u16, u16, u16)>>();
+
+        assert_eq!(outer, vec![(0, 500, 500), (500, 500, 500)]);
+        assert!(outer[0].0 + outer[0].1 <= outer[1].0);
+    }
+
+    #[test]
+    fn a_border_wider_than_the_area_keeps_the_window_visible() {
+        assert_eq!(inside_border(Area::new(10, 10, 6, 100), 4), Area::new(10, 10, 1, 92));
+    }
+
+    #[test]
+    fn frame_extents_grow_the_applied_geometry() {
+        let tree = leaf(1);
+        let frames = HashMap::from([(1, Padding { top: 5, bottom: 5, left: 10, right: 10 })]);
+
+        let layout = [(1, Area::new(20, 20, 100, 100))];
+
+        let changed = tree.changed(&layout, |_| 0, &frames, &mut HashMap::new());
+
+        assert_eq!(changed.into_iter().map(|(_, frame, _)| frame).collect::<Vec<Area>>(), vec![Area::new(10, 15, 120, 110)]);
+    }
+
+    #[test]
+    fn insert_splits_the_anchored_leaf_rather_than_the_focus() {
+        let (anchor, focus) = (2, 4);
+
+        let mut tree = nested();
+
+        tree.insert(5, Insert::new(Direction::South, 30), Point::Window(anchor));
+
+        assert_eq!(tree.collect(), vec![1, 2, 5, 3, 4]);
+        assert_eq!(tree.depth(5), Some(3));
+        assert_eq!(tree.depth(focus), Some(3));
+
+        assert_eq!(window(tree.jump(5, &[Jump::Brother])), Some(anchor));
+        assert_eq!(splits(tree.jump(5, &[Jump::Parent]).unwrap()), vec![Split::Vertical]);
+    }
+
+    #[test]
+    fn insert_puts_the_window_on_the_side_of_the_direction() {
+        let mut tree = split(leaf(1), leaf(2), Split::Horizontal);
+
+        tree.insert(3, Insert::new(Direction::West, 50), Point::Window(2));
+        tree.insert(4, Insert::new(Direction::East, 50), Point::Any);
+
+        assert_eq!(tree.collect(), vec![1, 4, 3, 2]);
+    }
+
+    #[test]
+    fn insert_next_to_a_missing_window_does_nothing() {
+        let mut tree = nested();
+
+        tree.insert(5, Insert::default(), Point::Window(6));
+
+        assert_eq!(tree.collect(), vec![1, 2, 3, 4]);
+    }
+
+    /// `1 | (R / R)`, a layout of three slots loaded with a single window
+    fn receptacles() -> Node<u32> {
+        let template = split(leaf(1), split(leaf(2), leaf(3), Split::Vertical), Split::Horizontal).template();
+
+        template.fill(&mut VecDeque::from([1]))
+    }
+
+    #[test]
+    fn fill_leaves_receptacles_for_the_missing_windows() {
+        let tree = receptacles();
+
+        assert_eq!(tree.collect(), vec![1]);
+        assert_eq!(render(&tree), ["horizontal 50%", "├── 0x1 ", "└── vertical 50%", "    ├── receptacle", "    └── receptacle"].join("\n"));
+    }
+
+    #[test]
+    fn receptacles_keep_their_area_empty() {
+        let layout = receptacles().layout(Area::new(0, 0, 200, 100), 0);
+
+        assert_eq!(areas(layout), vec![(0, 0, 100, 100)]);
+    }
+
+    #[test]
+    fn insert_fills_the_first_receptacle_instead_of_splitting() {
+        let mut tree = receptacles();
+
+        tree.insert(2, Insert::default(), Point::Window(1));
+        tree.insert(3, Insert::default(), Point::Window(1));
+
+        assert_eq!(tree.collect(), vec![1, 2, 3]);
+        assert_eq!(splits(&tree), vec![Split::Horizontal, Split::Vertical]);
+
+        tree.insert(4, Insert::new(Direction::East, 50), Point::Window(1));
+
+        assert_eq!(tree.depth(4), Some(2));
+    }
+
+    #[test]
+    fn a_tree_of_receptacles_has_no_first_window() {
+        let tree = split(leaf(1), leaf(2), Split::Horizontal).template().fill::<u32>(&mut VecDeque::new());
+
+        assert_eq!(tree.first(), None);
+        assert!(tree.prune().is_none());
+    }
+
+    #[test]
+    fn prune_collapses_the_splits_of_receptacles() {
+        let tree = receptacles().prune().unwrap();
+
+        assert_eq!(window(Some(&tree)), Some(1));
+    }
+
+    fn ratios(node: &Node<u32>) -> Vec<i8> {
+        match node {
+            Node::Leaf { .. } | Node::Receptacle => Vec::new(),
+            Node::Internal { left, right, ratio, .. } => [vec![*ratio], ratios(left), ratios(right)].concat(),
+        }
+    }
+
+    /// `(1 / 2) | (3 / 4)`
+    fn quad() -> Node<u32> {
+        split(split(leaf(1), leaf(2), Split::Vertical), split(leaf(3), leaf(4), Split::Vertical), Split::Horizontal)
+    }
+
+    #[test]
+    fn expand_the_top_left_of_a_grid_moves_both_axes() {
+        let mut tree = quad();
+
+        assert!(tree.expand(1, 10, RatioBounds::default()));
+
+        assert_eq!(ratios(&tree), vec![60, 60, 50]);
+
+        let area = Area::new(0, 0, 1000, 1000);
+
+        assert_eq!(areas(tree.layout(area, 0))[0], (0, 0, 600, 600));
+    }
+
+    #[test]
+    fn expand_the_bottom_right_moves_the_splits_the_other_way() {
+        let mut tree = quad();
+
+        assert!(tree.expand(4, 10, RatioBounds::default()));
+
+        assert_eq!(ratios(&tree), vec![40, 50, 40]);
+    }
+
+    #[test]
+    fn contract_is_clamped_to_the_ratio_bounds() {
+        let mut tree = quad();
+
+        assert!(tree.expand(1, -100, RatioBounds::default()));
+
+        assert_eq!(ratios(&tree), vec![10, 10, 50]);
+    }
+
+    #[test]
+    fn expand_a_missing_window_changes_nothing() {
+        let mut tree = quad();
+
+        assert!(!tree.expand(5, 10, RatioBounds::default()));
+
+        assert_eq!(ratios(&tree), vec![50, 50, 50]);
+    }
+
+    #[test]
+    fn tiled_point_falls_back_to_the_last_tiled_window() {
+        let (floating, last_tiled) = (9, 3);
+
+        let tree = nested();
+
+        assert_eq!(point(tree.tiled_point(Point::Window(floating), Some(last_tiled))), Some(last_tiled));
+        assert_eq!(point(tree.tiled_point(Point::Window(floating), Some(7))), None);
+        assert_eq!(point(tree.tiled_point(Point::Window(floating), None)), None);
+        assert_eq!(point(tree.tiled_point(Point::Window(2), Some(last_tiled))), Some(2));
+    }
+
+    #[test]
+    fn inserting_a_tiled_window_while_focus_is_floating() {
+        let (floating, last_tiled) = (9, 2);
+
+        let mut tree = nested();
+
+        let point = tree.tiled_point(Point::Window(floating), Some(last_tiled));
+
+        tree.insert(5, Insert::new(Direction::East, 50), point);
+
+        assert_eq!(tree.collect(), vec![1, 2, 5, 3, 4]);
+        assert_eq!(window(tree.jump(5, &[Jump::Brother])), Some(last_tiled));
+
+        // without a tiled window to go next to it still ends up in the tree
+        let point = tree.tiled_point(Point::Window(floating), None);
+
+        tree.insert(6, Insert::new(Direction::East, 50), point);
+
+        assert!(tree.contains(&6));
+    }
+
+    #[test]
+    fn dropping_a_grabbed_window_next_to_the_target() {
+        let (grabbed, target) = (1, 4);
+
+        let mut tree = nested();
+
+        // a drop takes the window out of its slot and splits the target with it
+        tree.remove(grabbed);
+
+        tree.insert(grabbed, Insert::new(Direction::West, 50), Point::Window(target));
+
+        assert_eq!(tree.collect(), vec![2, 3, 1, 4]);
+        assert_eq!(window(tree.jump(grabbed, &[Jump::Brother])), Some(target));
+        assert_eq!(tree.depth(grabbed), tree.depth(target));
+    }
+}
@@ -1,21 +1,35 @@
-use crate::config::{Configuration, Insert, Padding};
+use crate::config::{Configuration, Insert, Padding, Mouse, Border, Preset, RatioBounds};
 use crate::event::{Queue, EventType};
-use crate::tree::{Node, Point};
+use crate::client::{self, Client};
+use crate::query::{DesktopQuery, MonitorQuery, DesktopDump, ScreenDump, Dump, WindowInfo, TreeNode};
+use crate::tree::{self, Node, Point, Split, Template};
 use crate::startup;
-use crate::server;
+use crate::desktop;
+use crate::server::{self, Subscribers};
 
 use yaxi::display::{self, Display, Atom};
-use yaxi::window::{Window, WindowKind, WindowArguments, ValuesBuilder};
-use yaxi::proto::{Event, EventMask, RevertTo, ClientMessageData, WindowClass};
+use yaxi::window::{Window, WindowKind, WindowArguments, WindowValue, ValuesBuilder};
+use yaxi::proto::{Event, EventMask, RevertTo, ClientMessageData, WindowClass, PropFormat, PropMode};
 use yaxi::ewmh::DesktopViewport;
 
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::cell::Cell;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::process;
 use std::thread;
+use std::time::{Duration, Instant};
+use std::fs;
 
-use ipc::{Arguments, Command, NodeCommand, DesktopCommand, ConfigCommand, Change, State};
+use serde::Serialize;
 
+use ipc::{Arguments, Command, NodeCommand, DesktopCommand, ConfigCommand, QueryCommand, WmCommand, Change, GapChange, State, Direction, SnapPos, Pos, InsertMode, LayoutMode, Overflow, HookEvent, WrapMode, Layer, Jump, Response, Selector, Descriptor, Modifier};
 
-#[derive(Clone, Copy)]
+
+/// the smallest width or height left for tiling when the padding doesn't fit the screen
+const MIN_TILE_SIZE: u16 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub struct Area {
     pub x: u16,
     pub y: u16,
@@ -33,14 +47,156 @@ impl Area {
         }
     }
 
+    pub fn center(&self) -> (i32, i32) {
+        (self.x as i32 + self.width as i32 / 2, self.y as i32 + self.height as i32 / 2)
+    }
+
+    /// the distance to the other area if it lies in the direction, the offset across the
+    /// direction counts double so windows in line win over closer diagonal ones
+    pub fn distance_toward(&self, other: &Area, dir: Direction) -> Option<i32> {
+        let ((x, y), (ox, oy)) = (self.center(), other.center());
+
+        let (along, across) = match dir {
+            Direction::East => (ox - x, oy - y),
+            Direction::West => (x - ox, oy - y),
+            Direction::South => (oy - y, ox - x),
+            Direction::North => (y - oy, ox - x),
+        };
+
+        (along > 0).then(|| along + across.abs() * 2)
+    }
+
     pub fn contains(&self, x: u16, y: u16) -> bool {
         (x > self.x && x < self.x + self.width) && (y > self.y && y < self.y + self.height)
     }
+
+    /// the side of the area that the point is closest to
+    pub fn nearest_edge(&self, x: u16, y: u16) -> Direction {
+        let dx = (x as f64 - self.x as f64) / (self.width as f64).max(1.0);
+        let dy = (y as f64 - self.y as f64) / (self.height as f64).max(1.0);
+
+        [(dx, Direction::West), (1.0 - dx, Direction::East), (dy, Direction::North), (1.0 - dy, Direction::South)]
+            .into_iter()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, dir)| dir)
+            .unwrap_or(Direction::East)
+    }
+
+    fn factor(ratio: i8) -> f64 {
+        ratio.clamp(0, 100) as f64 / 100.0
+    }
+
+    /// splits the area into a left and right half, the left half gets `ratio` percent of the width
+    pub fn split_horizontal(&self, ratio: i8) -> (Area, Area) {
+        let width = (self.width as f64 * Area::factor(ratio)) as u16;
+
+        (
+            Area::new(self.x, self.y, width, self.height),
+            Area::new(self.x + width, self.y, self.width - width, self.height),
+        )
+    }
+
+    /// splits the area into a top and bottom half, the top half gets `ratio` percent of the height
+    pub fn split_vertical(&self, ratio: i8) -> (Area, Area) {
+        let height = (self.height as f64 * Area::factor(ratio)) as u16;
+
+        (
+            Area::new(self.x, self.y, self.width, height),
+            Area::new(self.x, self.y + height, self.width, self.height - height),
+        )
+    }
+
+    /// whether the padding leaves any room at all, the shrunk area is clamped when it doesn't
+    pub fn fits(&self, padding: Padding) -> bool {
+        padding.left.saturating_add(padding.right) < self.width && padding.top.saturating_add(padding.bottom) < self.height
+    }
+
+    pub fn shrink(&self, padding: Padding) -> Area {
+        let (x, width) = Area::clamp_axis(self.x, self.width, padding.left, padding.right);
+        let (y, height) = Area::clamp_axis(self.y, self.height, padding.top, padding.bottom);
+
+        Area::new(x, y, width, height)
+    }
+
+    /// keeps at least `MIN_TILE_SIZE` of the axis on screen, the leading padding gives way first
+    fn clamp_axis(start: u16, length: u16, leading: u16, trailing: u16) -> (u16, u16) {
+        let minimum = MIN_TILE_SIZE.min(length);
+        let leading = leading.min(length - minimum);
+
+        (start + leading, (length - leading).saturating_sub(trailing).max(minimum))
+    }
+
+    /// the half or quarter of the area described by `position`
+    pub fn snap(&self, position: SnapPos) -> Area {
+        let (width, height) = (self.width / 2, self.height / 2);
+
+        let (x, y, width, height) = match position {
+            SnapPos::Center => (self.width / 4, self.height / 4, width, height),
+            SnapPos::Left => (0, 0, width, self.height),
+            SnapPos::Right => (width, 0, self.width - width, self.height),
+            SnapPos::Top => (0, 0, self.width, height),
+            SnapPos::Bottom => (0, height, self.width, self.height - height),
+            SnapPos::TopLeft => (0, 0, width, height),
+            SnapPos::TopRight => (width, 0, self.width - width, height),
+            SnapPos::BottomLeft => (0, height, width, self.height - height),
+            SnapPos::BottomRight => (width, height, self.width - width, self.height - height),
+        };
+
+        Area::new(self.x + x, self.y + y, width, height)
+    }
+
+    /// the opposite of `shrink`, the area grows by the padding as far as the origin allows
+    pub fn expand(&self, padding: Padding) -> Area {
+        let (left, top) = (padding.left.min(self.x), padding.top.min(self.y));
+
+        Area::new(
+            self.x - left,
+            self.y - top,
+            self.width.saturating_add(left + padding.right),
+            self.height.saturating_add(top + padding.bottom),
+        )
+    }
+
+    pub fn inset(&self, gaps: u16) -> Area {
+        Area::new(
+            self.x + gaps,
+            self.y + gaps,
+            self.width.saturating_sub(gaps * 2),
+            self.height.saturating_sub(gaps * 2),
+        )
+    }
+}
+
+/// runs the command through the shell, the arguments are passed as `$1` and onwards
+fn spawn(command: &str, arguments: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .arg("sh")
+        .args(arguments)
+        .spawn()?;
+
+    // reap the child so it doesn't linger as a zombie once it exits
+    thread::spawn(move || child.wait());
+
+    Ok(())
 }
 
 pub struct Desktop {
     clients: Option<Node>,
     floating: Vec<Window>,
+    hidden: HashSet<u32>,
+    last_focus: Option<u32>,
+    last_tiled: Option<u32>,
+    pinned: HashMap<u32, (u16, u16)>,
+    layout: LayoutMode,
+    lazy_restack: bool,
+    bounds: RatioBounds,
+    frames: HashMap<u32, Padding>,
+    borderless: HashSet<u32>,
+    stacking: Vec<u32>,
+    fullscreen: Option<Window>,
+    applied: HashMap<u32, (Area, u16)>,
     area: Area,
 }
 
@@ -49,10 +205,88 @@ impl Desktop {
         Desktop {
             clients: None,
             floating: Vec::new(),
+            hidden: HashSet::new(),
+            last_focus: None,
+            last_tiled: None,
+            pinned: HashMap::new(),
+            layout: LayoutMode::Tiled,
+            lazy_restack: false,
+            bounds: RatioBounds::default(),
+            frames: HashMap::new(),
+            borderless: HashSet::new(),
+            stacking: Vec::new(),
+            fullscreen: None,
+            applied: HashMap::new(),
             area,
         }
     }
 
+    pub fn tree(&self, windows: &HashMap<u32, Client>) -> Option<TreeNode> {
+        self.clients.as_ref().map(|clients| TreeNode::new(clients, clients, windows))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.has_tiled() && self.floating.is_empty()
+    }
+
+    /// a tree that only holds receptacles has nothing to tile
+    pub fn has_tiled(&self) -> bool {
+        self.clients.as_ref().and_then(|clients| clients.first()).is_some()
+    }
+
+    pub fn windows(&self) -> Vec<Window> {
+        self.clients.iter()
+            .flat_map(|clients| clients.leaves())
+            .chain(self.floating.iter())
+            .cloned()
+            .collect()
+    }
+
+    pub fn dump(&self, name: Option<String>, windows: &HashMap<u32, Client>) -> DesktopDump {
+        DesktopDump {
+            name,
+            tree: self.tree(windows),
+            floating: self.floating.iter().map(|window| window.id()).collect(),
+            last_focus: self.last_focus,
+        }
+    }
+
+    /// the window that should receive focus when switching to this desktop
+    pub fn focus_target(&self) -> Option<u32> {
+        self.last_focus.or_else(|| Some(self.clients.as_ref()?.first()?.id()))
+    }
+
+    /// follows the selector path from a tiled window, internal nodes resolve to their first window
+    pub fn jump(&self, window: &Window, jumps: &[Jump]) -> Option<Window> {
+        self.clients.as_ref()?
+            .jump(window.id(), jumps)
+            .and_then(|node| node.first().cloned())
+    }
+
+    /// the topmost floating window or the tiled window that was focused last
+    pub fn layer_target(&self, layer: Layer) -> Option<u32> {
+        match layer {
+            Layer::Floating => self.floating.last().map(|window| window.id()),
+            Layer::Tiled => self.last_tiled.or_else(|| Some(self.clients.as_ref()?.first()?.id())),
+        }
+    }
+
+    pub fn is_floating(&self, window: &Window) -> bool {
+        self.floating.contains(window)
+    }
+
+    /// the window covers the whole desktop while it keeps its slot, so leaving fullscreen puts
+    /// it right back where it was
+    pub fn set_fullscreen(&mut self, window: &Window, enabled: bool) -> bool {
+        if !self.contains(window) || enabled == (self.fullscreen.as_ref() == Some(window)) {
+            return false;
+        }
+
+        self.fullscreen = enabled.then(|| window.clone());
+
+        true
+    }
+
     pub fn contains(&self, window: &Window) -> bool {
         match &self.clients {
             Some(clients) => clients.contains(window) || self.floating.contains(window),
@@ -62,11 +296,52 @@ impl Desktop {
 
     fn insert_tiled(&mut self, window: Window, insert: Insert, point: Point) {
         match &mut self.clients {
-            Some(clients) => clients.insert(window, insert, point),
+            Some(clients) => {
+                // a floating focus isn't in the tree, so the window goes next to the last tiled
+                // focus instead of getting lost
+                let point = clients.tiled_point(point, self.last_tiled);
+
+                clients.insert(window, insert, point)
+            },
             None => self.clients = Some(Node::root(window)),
         }
     }
 
+    pub fn template(&self) -> Option<Template> {
+        self.clients.as_ref().map(|clients| clients.template())
+    }
+
+    /// rebuilds the tree in the shape of the template, the slots left over become receptacles and
+    /// windows that don't fit are inserted as usual
+    pub fn apply_template(&mut self, template: Template) {
+        let mut windows = self.clients.as_ref()
+            .map(|clients| clients.collect())
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<VecDeque<Window>>();
+
+        self.clients = Some(template.fill(&mut windows));
+
+        for window in windows {
+            self.insert_tiled(window, Insert::default(), Point::Any);
+        }
+    }
+
+    /// fills the desktop with receptacles in the shape of the template, only allowed while empty
+    pub fn set_template(&mut self, template: Template) -> Result<(), Box<dyn std::error::Error>> {
+        if self.has_tiled() {
+            return Err("layout templates can only be applied to an empty desktop".into());
+        }
+
+        self.apply_template(template);
+
+        Ok(())
+    }
+
+    pub fn clear_receptacles(&mut self) {
+        self.clients = self.clients.take().and_then(Node::prune);
+    }
+
     pub fn insert(&mut self, window: Window, insert: Insert, point: Point, state: State) {
         match state {
             State::Float => self.floating.push(window),
@@ -78,6 +353,24 @@ impl Desktop {
     pub fn remove(&mut self, wid: impl Into<u32>) -> State {
         let wid = wid.into();
 
+        if self.last_focus == Some(wid) {
+            self.last_focus = None;
+        }
+
+        if self.last_tiled == Some(wid) {
+            self.last_tiled = None;
+        }
+
+        if self.fullscreen.as_ref().map(|window| window.id()) == Some(wid) {
+            self.fullscreen = None;
+        }
+
+        self.hidden.remove(&wid);
+
+        self.pinned.remove(&wid);
+
+        self.applied.remove(&wid);
+
         if self.clients.as_mut().map(|clients| clients.remove(wid)).unwrap_or(false) {
             self.clients = None;
         }
@@ -91,47 +384,184 @@ impl Desktop {
             .unwrap_or(State::Tiled)
     }
 
-    pub fn map_internal<F>(&mut self, wid: impl Into<u32>, f: F)
+    /// takes over every window of another desktop, used when its screen goes away
+    pub fn absorb(&mut self, other: Desktop) {
+        for window in other.clients.map(|clients| clients.collect()).unwrap_or_default() {
+            self.insert(window, Insert::default(), Point::Any, State::Tiled);
+        }
+
+        for window in other.floating {
+            self.insert(window, Insert::default(), Point::Any, State::Float);
+        }
+    }
+
+    pub fn map_internal<F>(&mut self, wid: impl Into<u32>, level: usize, f: F)
     where
-        F: Clone + Copy + Fn(Box<Node>, Box<Node>, Insert) -> Node
+        F: Clone + Copy + Fn(Box<Node>, Box<Node>, Split, i8) -> Node
     {
         if let Some(clients) = &mut self.clients {
-            clients.map_internal(wid.into(), f);
+            clients.map_internal(wid.into(), level, f);
         }
     }
 
-    pub fn hide(&self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(clients) = &self.clients {
-            clients.traverse(|window| {
-                window.unmap(WindowKind::Window).map_err(|err| err.into())
-            })?;
+    pub fn reorder(&mut self, wid: impl Into<u32>, pos: Pos) {
+        if let Some(clients) = &mut self.clients {
+            clients.reorder(wid.into(), pos);
         }
+    }
 
-        for window in self.floating.iter() {
+    pub fn replace(&mut self, wid: u32, window: Window) -> bool {
+        self.clients.as_mut().map(|clients| clients.replace(wid, window)).unwrap_or(false)
+    }
+
+    pub fn promote(&mut self, wid: u32) -> bool {
+        self.clients.as_mut().map(|clients| clients.promote(wid)).unwrap_or(false)
+    }
+
+    pub fn set_private(&mut self, wid: u32, toggle: bool) -> bool {
+        self.clients.as_mut().map(|clients| clients.set_private(wid, toggle)).unwrap_or(false)
+    }
+
+    pub fn rotate(&mut self, reverse: bool) {
+        if let Some(clients) = &mut self.clients {
+            clients.rotate(reverse);
+        }
+    }
+
+    pub fn grow(&mut self, wid: impl Into<u32>, padding: Padding, dir: Direction, px: i16) {
+        if let Some(clients) = &mut self.clients {
+            clients.grow(wid.into(), self.area.shrink(padding), dir, px, self.bounds);
+        }
+    }
+
+    pub fn expand(&mut self, wid: u32, ratio: i8) -> bool {
+        self.clients.as_mut().map(|clients| clients.expand(wid, ratio, self.bounds)).unwrap_or(false)
+    }
+
+    /// the area a tiled window currently occupies
+    pub fn area_of(&self, wid: u32, padding: Padding, gaps: u8) -> Option<Area> {
+        let clients = self.clients.as_ref()?;
+
+        // windows inserted since the last partition don't have an area yet
+        clients.cached_area(wid).or_else(|| {
+            clients.layout(self.area.shrink(padding), gaps)
+                .into_iter()
+                .find(|(window, _)| window.id() == wid)
+                .map(|(_, area)| area)
+        })
+    }
+
+    /// keeps the current size of a tiled window fixed across retiles
+    pub fn pin(&mut self, wid: u32, padding: Padding, gaps: u8) {
+        if let Some(area) = self.area_of(wid, padding, gaps) {
+            // the gaps are part of the leaf so they are added back on
+            self.pinned.insert(wid, (area.width + gaps as u16 * 2, area.height + gaps as u16 * 2));
+        }
+    }
+
+    pub fn unpin(&mut self, wid: u32) {
+        self.pinned.remove(&wid);
+    }
+
+    /// unmaps every window that is still mapped and returns their ids
+    pub fn hide(&mut self) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+        let windows = self.windows()
+            .into_iter()
+            .filter(|window| !self.hidden.contains(&window.id()))
+            .collect::<Vec<Window>>();
+
+        for window in windows.iter() {
             window.unmap(WindowKind::Window)?;
+
+            self.hidden.insert(window.id());
+
+            self.applied.remove(&window.id());
         }
 
-        Ok(())
+        Ok(windows.iter().map(|window| window.id()).collect())
     }
 
-    pub fn tile(&self, padding: Padding, gaps: u8) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(clients) = &self.clients {
-            let area = Area::new(
-                self.area.x + padding.left,
-                self.area.y + padding.top,
-                self.area.width - padding.left - padding.right,
-                self.area.height - padding.top - padding.bottom,
-            );
+    pub fn tile(&mut self, padding: Padding, gaps: u8, border: Border) -> Result<(), Box<dyn std::error::Error>> {
+        let focus = self.last_focus;
+        let borderless = &self.borderless;
+
+        let width = |window: &Window| match borderless.contains(&window.id()) {
+            true => 0,
+            false => border.tiled(Some(window.id()) == focus),
+        };
 
-            clients.partition(area, gaps)?;
+        if self.clients.is_some() && !self.area.fits(padding) {
+            eprintln!("yokai: the padding {:?} doesn't fit the screen {:?}, tiling in a clamped area", padding, self.area);
+        }
+
+        if let Some(clients) = &mut self.clients {
+            let layout = match self.layout {
+                LayoutMode::Tiled => {
+                    if !self.pinned.is_empty() {
+                        clients.constrain(self.area.shrink(padding), &self.pinned, self.bounds);
+                    }
+
+                    clients.layout(self.area.shrink(padding), gaps)
+                },
+                LayoutMode::MasterStack { master_factor, master_count } => {
+                    tree::master_stack(&clients.collect(), self.area.shrink(padding), master_factor, master_count, gaps)
+                },
+                LayoutMode::Grid { columns } => tree::grid(&clients.collect(), self.area.shrink(padding), columns, gaps),
+            };
+
+            // the fullscreen window keeps its slot but isn't moved into it, otherwise it would
+            // flicker between its tile and the whole screen on every retile
+            let layout = layout.into_iter()
+                .filter(|(window, _)| self.fullscreen.as_ref() != Some(window))
+                .collect();
+
+            clients.arrange(layout, width, &self.frames, &mut self.applied)?;
         }
 
         for window in self.floating.iter() {
             window.map(WindowKind::Window)?;
 
+            if !self.lazy_restack {
+                window.raise()?;
+            }
+        }
+
+        if self.lazy_restack {
+            self.restack()?;
+        }
+
+        // left out of the layout, so it goes back into its tile once it leaves fullscreen
+        if let Some(window) = &self.fullscreen {
+            window.set_border_width(0)?;
+
+            window.mov_resize(self.area.x, self.area.y, self.area.width, self.area.height)?;
+
             window.raise()?;
         }
 
+        self.hidden.clear();
+
+        Ok(())
+    }
+
+    /// raises the windows from the first one that is out of place, the tiled windows stay below
+    /// the floating ones
+    fn restack(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let desired = self.windows();
+
+        // mapping the windows of a hidden desktop puts them on top in whatever order
+        if !self.hidden.is_empty() {
+            self.stacking.clear();
+        }
+
+        let ids = desired.iter().map(|window| window.id()).collect::<Vec<u32>>();
+
+        for window in desired.iter().skip(desktop::restack_from(&self.stacking, &ids)) {
+            window.raise()?;
+        }
+
+        self.stacking = ids;
+
         Ok(())
     }
 }
@@ -140,6 +570,9 @@ pub struct Screen {
     desktops: Vec<Desktop>,
     current: usize,
     area: Area,
+    padding: Option<Padding>,
+    unmapped: Vec<u32>,
+    dirty: bool,
 }
 
 impl Screen {
@@ -148,13 +581,66 @@ impl Screen {
             desktops: Vec::new(),
             current: 0,
             area,
+            padding: None,
+            unmapped: Vec::new(),
+            dirty: false,
         }
     }
 
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
     pub fn contains(&self, window: &Window) -> bool {
         self.desktops.iter().any(|desktop| desktop.contains(window))
     }
 
+    /// whether the window is on the desktop the screen shows
+    pub fn is_visible(&self, window: &Window) -> bool {
+        self.desktops.get(self.current).map(|desktop| desktop.contains(window)).unwrap_or(false)
+    }
+
+    /// whether retiling would move anything, only the tiled windows depend on gaps and padding
+    pub fn has_tiled(&self) -> bool {
+        self.desktops.get(self.current).map(|desktop| desktop.has_tiled()).unwrap_or(false)
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut Desktop> {
+        self.desktops.get_mut(self.current)
+    }
+
+    pub fn set_area(&mut self, area: Area) {
+        self.area = area;
+
+        for desktop in self.desktops.iter_mut() {
+            desktop.area = area;
+        }
+    }
+
+    /// moves the desktops of a removed screen onto the desktops with the same index
+    pub fn absorb(&mut self, other: Screen) {
+        for (index, desktop) in other.desktops.into_iter().enumerate() {
+            let target = index.min(self.desktops.len().saturating_sub(1));
+
+            if let Some(target) = self.desktops.get_mut(target) {
+                target.absorb(desktop);
+            }
+        }
+    }
+
+    pub fn is_floating(&self, window: &Window) -> bool {
+        self.desktops.get(self.current).map(|desktop| desktop.is_floating(window)).unwrap_or(false)
+    }
+
+    pub fn is_fullscreen(&self, window: &Window) -> bool {
+        self.desktops.iter().any(|desktop| desktop.fullscreen.as_ref() == Some(window))
+    }
+
+    /// changes the fullscreen state on whichever desktop holds the window
+    pub fn set_fullscreen(&mut self, window: &Window, enabled: bool) -> bool {
+        self.desktops.iter_mut().any(|desktop| desktop.set_fullscreen(window, enabled))
+    }
+
     pub fn resize(&mut self, size: usize) {
         if size >= self.desktops.len() {
             self.desktops.resize_with(size, || Desktop::new(self.area));
@@ -169,6 +655,8 @@ impl Screen {
             for window in excess {
                 self.desktops[size - 1].insert(window, Insert::default(), Point::Any, State::Tiled);
             }
+
+            self.current = self.current.min(size.saturating_sub(1));
         }
     }
 
@@ -182,23 +670,94 @@ impl Screen {
         self.desktops[self.current].remove(wid)
     }
 
-    pub fn map_internal<F>(&mut self, wid: impl Into<u32>, f: F)
+    /// removes the window from whichever desktop it is on
+    pub fn forget(&mut self, wid: u32) {
+        for desktop in self.desktops.iter_mut() {
+            desktop.remove(wid);
+        }
+    }
+
+    /// puts the window into the slot of `wid` on whichever desktop holds it
+    pub fn replace(&mut self, wid: u32, window: Window) -> bool {
+        self.desktops.iter_mut().any(|desktop| desktop.replace(wid, window.clone()))
+    }
+
+    /// the padding of this screen, the global padding is used unless it has its own
+    pub fn padding(&self, fallback: Padding) -> Padding {
+        self.padding.unwrap_or(fallback)
+    }
+
+    pub fn remember_focus(&mut self, window: &Window) {
+        for desktop in self.desktops.iter_mut() {
+            if desktop.contains(window) {
+                desktop.last_focus = Some(window.id());
+
+                match desktop.floating.iter().position(|floating| floating == window) {
+                    // the floating windows are raised in order when tiling so the stacking is kept
+                    Some(index) => {
+                        let window = desktop.floating.remove(index);
+
+                        desktop.floating.push(window);
+                    },
+                    None => desktop.last_tiled = Some(window.id()),
+                }
+            }
+        }
+    }
+
+    pub fn dump(&self, names: &[String], windows: &HashMap<u32, Client>) -> ScreenDump {
+        ScreenDump {
+            area: self.area,
+            current: self.current,
+            desktops: self.desktops.iter()
+                .enumerate()
+                .map(|(index, desktop)| desktop.dump(names.get(index).cloned(), windows))
+                .collect(),
+        }
+    }
+
+    pub fn focus_target(&self) -> Option<u32> {
+        self.desktops.get(self.current).and_then(|desktop| desktop.focus_target())
+    }
+
+    pub fn layer_target(&self, layer: Layer) -> Option<u32> {
+        self.desktops.get(self.current).and_then(|desktop| desktop.layer_target(layer))
+    }
+
+    pub fn map_internal<F>(&mut self, wid: impl Into<u32>, level: usize, f: F)
     where
-        F: Clone + Copy + Fn(Box<Node>, Box<Node>, Insert) -> Node
+        F: Clone + Copy + Fn(Box<Node>, Box<Node>, Split, i8) -> Node
     {
         if let Some(desktop) = self.desktops.get_mut(self.current) {
-            desktop.map_internal(wid, f);
+            desktop.map_internal(wid, level, f);
         }
     }
 
-    pub fn tile(&self, padding: Padding, gaps: u8) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(desktop) = self.desktops.get(self.current) {
-            desktop.tile(padding, gaps)?;
+    pub fn area_of(&self, wid: u32, padding: Padding, gaps: u8) -> Option<Area> {
+        self.desktops.get(self.current)?.area_of(wid, self.padding(padding), gaps)
+    }
+
+    /// the tiled window under the point on the current desktop
+    pub fn leaf_at(&self, x: u16, y: u16, padding: Padding, gaps: u8) -> Option<(Window, Area)> {
+        let desktop = self.desktops.get(self.current)?;
+
+        desktop.clients.as_ref()?
+            .layout(desktop.area.shrink(self.padding(padding)), gaps)
+            .into_iter()
+            .find(|(_, area)| area.contains(x, y))
+    }
+
+    pub fn tile(&mut self, padding: Padding, gaps: u8, border: Border) -> Result<(), Box<dyn std::error::Error>> {
+        let padding = self.padding(padding);
+
+        if let Some(desktop) = self.desktops.get_mut(self.current).filter(|desktop| !desktop.is_empty()) {
+            desktop.tile(padding, gaps, border)?;
         }
 
-        for (index, desktop) in self.desktops.iter().enumerate() {
-            if index != self.current {
-                desktop.hide()?;
+        // empty desktops have nothing to hide, which adds up with many unused desktops
+        for (index, desktop) in self.desktops.iter_mut().enumerate() {
+            if index != self.current && !desktop.is_empty() {
+                self.unmapped.extend(desktop.hide()?);
             }
         }
 
@@ -206,9 +765,125 @@ impl Screen {
     }
 }
 
+pub struct Drag {
+    window: Window,
+    button: u8,
+    pointer: (u16, u16),
+    area: Area,
+    retile: bool,
+}
+
+/// interval between two frames of an animation, caps it at roughly 60 frames per second
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// a ratio change that is being interpolated, every frame retiles the desktop it started on
+pub struct Animation {
+    id: u64,
+    screen: usize,
+    desktop: usize,
+    wid: u32,
+    level: usize,
+    from: i8,
+    to: i8,
+    started: Instant,
+    duration: Duration,
+}
+
+impl Animation {
+    /// the ratio at this point in time, the target once the duration has passed
+    fn ratio(&self) -> i8 {
+        let progress = self.started.elapsed().as_secs_f32() / self.duration.as_secs_f32().max(f32::EPSILON);
+
+        match progress >= 1.0 {
+            true => self.to,
+            false => (self.from as f32 + (self.to as f32 - self.from as f32) * progress).round() as i8,
+        }
+    }
+}
+
+/// icccm `WM_STATE` values, withdrawn windows have the property removed instead
+pub const NORMAL_STATE: u32 = 1;
+pub const ICONIC_STATE: u32 = 3;
+
+const CONFIGURE_X: u16 = 1 << 0;
+const CONFIGURE_Y: u16 = 1 << 1;
+const CONFIGURE_WIDTH: u16 = 1 << 2;
+const CONFIGURE_HEIGHT: u16 = 1 << 3;
+const CONFIGURE_STACK_MODE: u16 = 1 << 6;
+
+const STACK_ABOVE: u8 = 0;
+
+/// the geometry a floating or unmanaged window gets from a `ConfigureRequest`, whatever the
+/// value mask leaves out stays as it is
+fn requested_area(value_mask: u16, x: i16, y: i16, width: u16, height: u16, current: Area) -> Area {
+    let pick = |bit: u16, requested: u16, current: u16| (value_mask & bit != 0).then_some(requested).unwrap_or(current);
+
+    Area::new(
+        pick(CONFIGURE_X, x.max(0) as u16, current.x),
+        pick(CONFIGURE_Y, y.max(0) as u16, current.y),
+        pick(CONFIGURE_WIDTH, width, current.width),
+        pick(CONFIGURE_HEIGHT, height, current.height),
+    )
+}
+
+/// only floating windows may restack themselves and only upwards, the tiler owns the order of
+/// tiled windows and lowering a floating window would hide it behind the tiles
+fn honors_restack(floating: bool, value_mask: u16, stack_mode: u8) -> bool {
+    floating && value_mask & CONFIGURE_STACK_MODE != 0 && stack_mode == STACK_ABOVE
+}
+
+/// the position that puts a window of this size in the middle of the area, a window larger than
+/// the area hangs over its far edges but never off the left or top of the screen
+fn centered(area: Area, width: u16, height: u16) -> (u16, u16) {
+    (
+        (area.x as i32 + (area.width as i32 - width as i32) / 2).max(0) as u16,
+        (area.y as i32 + (area.height as i32 - height as i32) / 2).max(0) as u16,
+    )
+}
+
+/// the desktop a window sent to `desktop` goes to and whether it has to be created first
+fn resolve_overflow(desktop: usize, length: usize, overflow: Overflow) -> Result<(usize, bool), Box<dyn std::error::Error>> {
+    match (desktop < length, overflow) {
+        (true, _) => Ok((desktop, false)),
+        (false, Overflow::Clamp) => Ok((length.saturating_sub(1), false)),
+        // only the next desktop can be created, a client could ask for any number
+        (false, Overflow::Create) if desktop == length => Ok((desktop, true)),
+        (false, Overflow::Create) => Err(format!("desktop {} does not exist, only desktop {} can be created", desktop, length).into()),
+        (false, Overflow::Reject) => Err(format!("desktop {} does not exist", desktop).into()),
+    }
+}
+
+/// the window the focus reverts to once the focused one is gone, the most recently focused one that
+/// is still available wins and `None` hands the focus to the root
+fn revert_focus<F>(history: &[u32], available: F, fallback: Option<u32>) -> Option<u32>
+where
+    F: Fn(u32) -> bool
+{
+    history.iter().rev().copied().find(|wid| available(*wid)).or(fallback)
+}
+
+/// what the focus becomes once `moved` left for another desktop, `None` leaves it alone since
+/// only moving the focused window away takes the focus with it
+fn focus_after_move(focus: Option<u32>, moved: u32, source: Option<u32>) -> Option<Option<u32>> {
+    (focus == Some(moved)).then_some(source)
+}
+
+/// a focus change forgets the grab, the grabbed window gaining focus itself doesn't count as one
+/// since it is usually focused already when it is grabbed
+fn grab_after_focus(grabbed: Option<u32>, focus: u32) -> Option<u32> {
+    grabbed.filter(|wid| *wid == focus)
+}
+
 pub struct Atoms {
     wm_protocols: Atom,
     wm_delete: Atom,
+    wm_take_focus: Atom,
+    wm_state: Atom,
+    gtk_frame_extents: Atom,
+    net_wm_state: Atom,
+    net_wm_state_fullscreen: Atom,
+    skip_taskbar: Atom,
+    skip_pager: Atom,
 }
 
 impl Atoms {
@@ -216,18 +891,59 @@ impl Atoms {
         Ok(Atoms {
             wm_protocols: display.intern_atom("WM_PROTOCOLS", false)?,
             wm_delete: display.intern_atom("WM_DELETE_WINDOW", false)?,
+            wm_take_focus: display.intern_atom("WM_TAKE_FOCUS", false)?,
+            wm_state: display.intern_atom("WM_STATE", false)?,
+            gtk_frame_extents: display.intern_atom("_GTK_FRAME_EXTENTS", false)?,
+            net_wm_state: display.intern_atom("_NET_WM_STATE", false)?,
+            net_wm_state_fullscreen: display.intern_atom("_NET_WM_STATE_FULLSCREEN", false)?,
+            skip_taskbar: display.intern_atom("_NET_WM_STATE_SKIP_TASKBAR", false)?,
+            skip_pager: display.intern_atom("_NET_WM_STATE_SKIP_PAGER", false)?,
         })
     }
 }
 
+/// another window manager already redirects the root window
+#[derive(Debug)]
+pub struct AlreadyRunning(String);
+
+impl std::fmt::Display for AlreadyRunning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "another window manager is already running ({})", self.0)
+    }
+}
+
+impl std::error::Error for AlreadyRunning {}
+
 pub struct WindowManager {
     display: Display,
     root: Window,
+    check: Option<Window>,
     focus: Option<Window>,
+    windows: HashMap<u32, Client>,
+    unmaps: HashMap<u32, usize>,
+    marks: HashMap<String, u32>,
+    history: Vec<u32>,
+    swallowed: HashMap<u32, Window>,
+    level: usize,
+    monitor: Option<usize>,
+    frozen: bool,
+    deferred: Vec<u32>,
+    hover: Option<u32>,
+    grabbed: Option<u32>,
+    active_screen: Option<usize>,
+    pending_focus: Option<u32>,
+    /// the latest server timestamp we have seen, `WM_TAKE_FOCUS` isn't allowed to use CurrentTime
+    time: u32,
     events: Arc<Queue<EventType>>,
+    subscribers: Arc<Subscribers>,
     screens: Vec<Screen>,
     config: Configuration,
     atoms: Atoms,
+    drag: Option<Drag>,
+    animation: Option<Animation>,
+    frames: u64,
+    presel: Option<Window>,
+    anchor: Option<Window>,
     should_close: bool,
 }
 
@@ -236,32 +952,77 @@ impl WindowManager {
         let display = display::open(None)?;
         let root = display.default_root_window()?;
 
-        root.select_input(&[
+        // only one client can redirect the root, everybody else gets a BadAccess which the round
+        // trip of the geometry request makes sure we see before going any further
+        let redirect = root.select_input(&[
+            EventMask::StructureNotify,
             EventMask::SubstructureNotify,
             EventMask::SubstructureRedirect,
             EventMask::EnterWindow,
             EventMask::FocusChange,
-        ])?;
+        ]).and_then(|_| root.get_geometry().map(|_| ()));
+
+        if let Err(err) = redirect {
+            return Err(Box::new(AlreadyRunning(err.to_string())));
+        }
 
         let atoms = Atoms::new(&display)?;
 
         Ok(WindowManager {
             display,
             root,
+            check: None,
             focus: None,
+            windows: HashMap::new(),
+            unmaps: HashMap::new(),
+            marks: HashMap::new(),
+            history: Vec::new(),
+            swallowed: HashMap::new(),
+            level: 0,
+            monitor: None,
+            frozen: false,
+            deferred: Vec::new(),
+            hover: None,
+            grabbed: None,
+            active_screen: None,
+            pending_focus: None,
+            time: 0,
             events: Arc::new(Queue::new()),
+            subscribers: Arc::new(Subscribers::new()),
             screens: Vec::new(),
             config: Configuration::new(),
             atoms,
+            drag: None,
+            animation: None,
+            frames: 0,
+            presel: None,
+            anchor: None,
             should_close: false,
         })
     }
 
+    fn query_screens(&self) -> Result<Vec<Area>, Box<dyn std::error::Error>> {
+        Ok(self.display.query_xinerama()?
+            .query_screens()?
+            .iter()
+            .map(|screen| Area::new(screen.x, screen.y, screen.width, screen.height))
+            .collect())
+    }
+
     fn load_screens(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let xinerama = self.display.query_xinerama()?;
+        match self.query_screens() {
+            Ok(areas) if !areas.is_empty() => {
+                eprintln!("yokai: xinerama found {} screens", areas.len());
+
+                self.screens.extend(areas.into_iter().map(Screen::new));
+            },
+            _ => {
+                let geometry = self.root.get_geometry()?;
 
-        for screen in xinerama.query_screens()? {
-            self.screens.push(Screen::new(Area::new(screen.x, screen.y, screen.width, screen.height)));
+                eprintln!("yokai: xinerama is unavailable, using the root window as a single screen");
+
+                self.screens.push(Screen::new(Area::new(0, 0, geometry.width, geometry.height)));
+            },
         }
 
         self.update_viewport()?;
@@ -269,17 +1030,73 @@ impl WindowManager {
         Ok(())
     }
 
-    fn update_viewport(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let viewport = self.screens.iter()
-            .map(|screen| DesktopViewport::new(screen.area.x as u32, screen.area.y as u32))
-            .collect::<Vec<DesktopViewport>>();
+    /// randr resizes the root window whenever outputs change, so we re-read the monitors and
+    /// carry the windows of removed monitors over to the first surviving one
+    fn reconcile_screens(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let areas = match self.query_screens() {
+            Ok(areas) if !areas.is_empty() => areas,
+            _ => {
+                let geometry = self.root.get_geometry()?;
 
-        self.display
-            .use_ewmh(&self.root)
-            .set_desktop_viewport(&viewport)?;
+                vec![Area::new(0, 0, geometry.width, geometry.height)]
+            },
+        };
 
-        Ok(())
-    }
+        let length = self.config.desktops.names.len();
+
+        if areas.len() < self.screens.len() {
+            let removed = self.screens.drain(areas.len()..).collect::<Vec<Screen>>();
+
+            for screen in removed {
+                self.screens[0].absorb(screen);
+            }
+        }
+
+        for (index, area) in areas.into_iter().enumerate() {
+            match self.screens.get_mut(index) {
+                Some(screen) => screen.set_area(area),
+                None => {
+                    let mut screen = Screen::new(area);
+
+                    screen.resize(length);
+
+                    self.screens.push(screen);
+                },
+            }
+        }
+
+        eprintln!("yokai: screens changed, now managing {} screens", self.screens.len());
+
+        self.sync_desktops();
+
+        if self.config.ewmh {
+            self.display
+                .use_ewmh(&self.root)
+                .set_number_of_desktops((length * self.screens.len()) as u32)?;
+        }
+
+        self.update_viewport()?;
+
+        self.mark_dirty();
+
+        Ok(())
+    }
+
+    fn update_viewport(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.config.ewmh {
+            return Ok(());
+        }
+
+        let viewport = self.screens.iter()
+            .map(|screen| DesktopViewport::new(screen.area.x as u32, screen.area.y as u32))
+            .collect::<Vec<DesktopViewport>>();
+
+        self.display
+            .use_ewmh(&self.root)
+            .set_desktop_viewport(&viewport)?;
+
+        Ok(())
+    }
 
     fn set_supporting_ewmh(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let window = self.root.create_window(WindowArguments {
@@ -300,18 +1117,29 @@ impl WindowManager {
 
         ewmh.set_wm_name("yokai")?;
 
+        // `wmctrl -m` and friends identify the window manager by the class of the check window
+        let class = self.display.intern_atom("WM_CLASS", false)?;
+
+        window.change_property(class, Atom::STRING, PropFormat::Format8, PropMode::Replace, b"yokai\0yokai\0")?;
+
         let root = self.display.use_ewmh(&self.root);
 
         root.set_supporting_wm_check(window.id())?;
 
-        // TODO: support for _NET_WM_STATE and _NET_WM_STATE_FULLSCREEN
+        self.check = Some(window);
 
         root.set_supported(&[
+            self.display.intern_atom("_NET_WM_STATE", false)?,
+            self.display.intern_atom("_NET_WM_STATE_FULLSCREEN", false)?,
             self.display.intern_atom("WM_PROTOCOLS", false)?,
             self.display.intern_atom("WM_DELETE_WINDOW", false)?,
+            self.display.intern_atom("WM_TAKE_FOCUS", false)?,
+            self.display.intern_atom("_NET_SUPPORTING_WM_CHECK", false)?,
+            self.display.intern_atom("_NET_WM_NAME", false)?,
             self.display.intern_atom("_NET_ACTIVE_WINDOW", false)?,
             self.display.intern_atom("_NET_NUMBER_OF_DESKTOPS", false)?,
             self.display.intern_atom("_NET_CURRENT_DESKTOP", false)?,
+            self.display.intern_atom("_NET_FRAME_EXTENTS", false)?,
             self.display.intern_atom("_NET_WM_WINDOW_TYPE", false)?,
             self.display.intern_atom("_NET_WM_WINDOW_TYPE_DESKTOP", false)?,
             self.display.intern_atom("_NET_WM_WINDOW_TYPE_DOCK", false)?,
@@ -342,6 +1170,12 @@ impl WindowManager {
         F: FnMut(usize, &mut Screen) -> Result<R, Box<dyn std::error::Error>>,
         R: Default,
     {
+        if let Some(index) = self.monitor {
+            return self.screens.get_mut(index)
+                .map(|screen| f(index, screen))
+                .unwrap_or_else(|| Ok(R::default()));
+        }
+
         let pointer = self.root.query_pointer()?;
 
         for (index, screen) in self.screens.iter_mut().enumerate() {
@@ -353,377 +1187,2739 @@ impl WindowManager {
         Ok(R::default())
     }
 
-    // TODO: we should be able to remove this function as focus should never be root because we
-    // only allow windows that are managed by us to become focused
-    fn map_focus<F>(&self, mut f: F) -> Result<(), Box<dyn std::error::Error>>
-    where
-        F: FnMut(&Window) -> Result<(), Box<dyn std::error::Error>>
-    {
-        match &self.focus {
-            Some(focus) if focus != &self.root => f(focus),
-            _ => Ok(()),
+    /// defers a retile of every screen until the event queue has drained
+    pub fn mark_dirty(&mut self) {
+        for screen in self.screens.iter_mut() {
+            screen.mark_dirty();
         }
     }
 
-    fn is_managed(&self, window: &Window) -> bool {
-        self.screens.iter().any(|screen| screen.contains(window))
-    }
+    pub fn flush_tiles(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        // the screens stay dirty so thawing retiles everything that changed in the meantime
+        if self.frozen {
+            return Ok(());
+        }
 
-    fn handle_event(&mut self, event: Event) -> Result<(), Box<dyn std::error::Error>> {
-        println!("event: {:?}", event);
+        let padding = self.config.padding.clone();
+        let gaps = self.config.gaps.clone();
+        let border = self.config.border;
 
-        match event {
-            Event::MapRequest { window, .. } => {
-                let focus = self.focus.clone();
-                let window = self.display.window_from_id(window)?;
-                let insert = self.config.insert.clone();
-                let padding = self.config.padding.clone();
-                let gaps = self.config.gaps.clone();
+        self.all(|_, screen| {
+            if std::mem::take(&mut screen.dirty) {
+                screen.tile(padding, gaps, border)?;
+            }
 
-                window.select_input(&[
-                    EventMask::SubstructureNotify,
-                    EventMask::SubstructureRedirect,
-                    EventMask::EnterWindow,
-                    EventMask::FocusChange,
-                ])?;
+            Ok(())
+        })
+    }
 
-                window.set_border_pixel(self.config.border.normal)?;
+    /// applies the border matching the focus and floating state of the window
+    fn set_border(&self, window: &Window, focused: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let floating = self.screens.iter().any(|screen| screen.is_floating(window));
+        let borderless = self.windows.get(&window.id()).map(|client| client.borderless).unwrap_or(false)
+            || self.screens.iter().any(|screen| screen.is_fullscreen(window));
 
-                window.set_border_width(self.config.border.width)?;
+        let focused_only = self.config.border.focused_only && !focused;
 
-                let types = self.display
-                    .use_ewmh(&window)
-                    .get_wm_window_type()?;
+        let width = if borderless || focused_only { 0 } else { self.config.border.width(floating) };
 
-                self.focused(|_, screen| {
-                    screen.insert(
-                        window.clone(),
-                        insert.clone(),
-                        focus.clone()
-                            .map(|focus| Point::Window(focus))
-                            .unwrap_or(Point::Any),
-                        State::from(&types),
-                    );
-
-                    screen.tile(padding, gaps)
-                })?;
-            },
-            Event::UnmapNotify { window, .. } => {
-                let padding = self.config.padding.clone();
-                let gaps = self.config.gaps.clone();
+        window.set_border_pixel(self.config.border.pixel(focused, floating))?;
 
-                self.all(|_, screen| {
-                    screen.remove(window);
+        window.set_border_width(width)?;
 
-                    screen.tile(padding, gaps)
-                })?;
+        // only the window that changed is resized instead of retiling the whole desktop
+        if self.config.border.focused_only && !floating && !borderless {
+            let area = self.screens.iter()
+                .find_map(|screen| screen.area_of(window.id(), self.config.padding, self.config.gaps));
 
-                if self.focus.as_ref().map(|window| window.id()) == Some(window) {
-                    self.focus = None;
-                }
-            },
-            Event::EnterNotify { window, .. } => {
-                let window = self.display.window_from_id(window)?;
+            if let Some(area) = area {
+                window.mov_resize(area.x, area.y, area.width.saturating_sub(width * 2).max(1), area.height.saturating_sub(width * 2).max(1))?;
+            }
+        }
 
-                if self.is_managed(&window) && self.config.pf.focus_follows {
-                    window.set_input_focus(RevertTo::Parent)?;
-                }
-            },
-            Event::FocusIn { window, .. } => {
-                let window = self.display.window_from_id(window)?;
+        // we don't reparent so the border is the only decoration a client has to account for
+        self.display
+            .use_ewmh(window)
+            .set_frame_extents(width as u32, width as u32, width as u32, width as u32)?;
 
-                if self.is_managed(&window) {
-                    window.set_border_pixel(self.config.border.focused)?;
+        Ok(())
+    }
 
-                    if let Some(focus) = self.focus.replace(window.clone()) {
-                        if focus.id() != window.id() {
-                            focus.set_border_pixel(self.config.border.normal)?;
-                        }
-                    }
-                }
-            },
-            _ => {},
+    /// reapplies the border of every window on the screen without retiling it
+    fn refresh_borders(&self, index: usize) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(screen) = self.screens.get(index) {
+            for window in screen.desktops.iter().flat_map(|desktop| desktop.windows()) {
+                self.set_border(&window, self.focus.as_ref() == Some(&window))?;
+            }
         }
 
         Ok(())
     }
 
-    fn handle_config(&mut self, args: Arguments) -> Result<(), Box<dyn std::error::Error>> {
-        // TODO: we need to implement node selection, right now we automatically select the focused
-        // node but we want to make it possible for the user to e.g. select the brother node,
-        // parent node and so on.
-        //
-        // this should be a priority before we implement more.
-        //
-        // we will have to implement every selector that bspwm supports.
-        //
-        // we need a function that takes a selector and returns a node if it exists
-        //
-        // TODO: we can represent floating windows as a part of the tree to, how we do this is that
-        // we only only split the area if neither of the leafs are floating
+    /// retiles the screens that have tiled windows on them, the others would not change
+    fn retile_tiled(&mut self) {
+        for screen in self.screens.iter_mut().filter(|screen| screen.has_tiled()) {
+            screen.mark_dirty();
+        }
+    }
 
-        println!("config: {:?}", args);
+    /// clients that manage their own focus are also asked to take it through `WM_TAKE_FOCUS`,
+    /// should the window vanish before we pick a new focus x falls back to the pointer root
+    fn give_focus(&self, window: &Window) -> Result<(), Box<dyn std::error::Error>> {
+        window.set_input_focus(RevertTo::PointerRoot)?;
+
+        if self.get_u32_property(window, "WM_PROTOCOLS", Atom::ATOM)?.contains(&self.atoms.wm_take_focus.id()) {
+            window.send_event(Event::ClientMessage {
+                format: 32,
+                window: window.id(),
+                type_: self.atoms.wm_protocols.clone(),
+                data: ClientMessageData::Long([
+                    self.atoms.wm_take_focus.id(),
+                    self.time,
+                    0,
+                    0,
+                    0,
+                ]),
+            }, Vec::new(), false)?;
+        }
 
-        match args.command {
-            Command::Node { node, selector } => match node {
-                NodeCommand::Insert { dir, ratio, toggle } => {
-                    let insert = Insert::new(dir, ratio.unwrap_or(self.config.insert.ratio));
+        Ok(())
+    }
 
-                    self.config.insert = (insert == self.config.insert && toggle)
-                        .then(|| Insert::default())
-                        .unwrap_or(insert);
-                },
-                NodeCommand::Move { dx, dy } => {
-                    // TODO: support negative numbers
-                    if let Some(focus) = self.focus.clone() {
-                        let geometry = focus.get_geometry()?;
+    fn set_focus(&mut self, window: Window) -> Result<(), Box<dyn std::error::Error>> {
+        self.give_focus(&window)?;
 
-                        focus.mov((geometry.x as i32 + dx).max(0) as u16, (geometry.y as i32 + dy).max(0) as u16)?;
-                    }
-                },
-                NodeCommand::State { state, toggle } => {
-                    if let Some(focus) = self.focus.clone() {
-                        let insert = self.config.insert.clone();
-                        let padding = self.config.padding.clone();
-                        let gaps = self.config.gaps.clone();
-
-                        self.focused(|_, screen| {
-                            if screen.remove(focus.id()) == state && toggle {
-                                screen.insert(focus.clone(), insert, Point::Any, state.toggle());
-                            } else {
-                                screen.insert(focus.clone(), insert, Point::Any, state);
-                            }
+        self.set_border(&window, true)?;
 
-                            screen.tile(padding, gaps)
-                        })?;
-                    }
-                },
-                NodeCommand::Desktop { desktop } => {
-                    if let Some(focus) = self.focus.clone() {
-                        if self.focused(|_, screen| Ok(desktop < screen.desktops.len() && screen.current != desktop))? {
-                            let insert = self.config.insert.clone();
-                            let padding = self.config.padding.clone();
-                            let gaps = self.config.gaps.clone();
-                            let wid = focus.id();
+        if let Some(focus) = self.focus.replace(window.clone()) {
+            if focus.id() != window.id() {
+                self.set_border(&focus, false)?;
 
-                            self.focused(move |_, screen| {
-                                let state = screen.remove(wid);
+                self.level = 0;
+            }
+        }
 
-                                screen.desktops[desktop].insert(focus.clone(), insert, Point::Any, state);
+        Ok(())
+    }
 
-                                screen.tile(padding, gaps)
-                            })?;
+    /// the tiled terminal of a local process that started the window
+    fn swallower(&self, wid: u32) -> Option<Window> {
+        let ancestors = client::ancestors(self.windows.get(&wid)?.local_pid()?);
+
+        let is_terminal = |client: &Client| [&client.class, &client.instance]
+            .into_iter()
+            .flatten()
+            .any(|name| self.config.terminals.iter().any(|terminal| terminal.eq_ignore_ascii_case(name)));
+
+        // only a terminal the user can see gives up its slot, e.g. not a file manager or a
+        // terminal on another desktop
+        self.screens.iter()
+            .filter_map(|screen| screen.desktops.get(screen.current))
+            .flat_map(|desktop| desktop.clients.iter().flat_map(|clients| clients.leaves()))
+            .find(|leaf| {
+                self.windows.get(&leaf.id())
+                    .filter(|client| is_terminal(client))
+                    .and_then(|client| client.local_pid())
+                    .map(|pid| ancestors.contains(&pid))
+                    .unwrap_or(false)
+            })
+            .cloned()
+    }
 
-                            if self.focus.as_ref().map(|window| window.id()) == Some(wid) {
-                                self.focus = None;
-                            }
-                        }
-                    }
-                },
-                NodeCommand::Ratio { change } => {
-                    if let Some(focus) = self.focus.clone() {
-                        let padding = self.config.padding.clone();
-                        let gaps = self.config.gaps.clone();
-
-                        self.focused(move |_, screen| {
-                            screen.map_internal(focus.id(), |left, right, insert| {
-                                Node::Internal {
-                                    left,
-                                    right,
-                                    insert: Insert {
-                                        dir: insert.dir,
-                                        ratio: match change {
-                                            Change::Add { value } => insert.ratio + value,
-                                            Change::Sub { value } => insert.ratio - value.min(insert.ratio),
-                                            Change::Set { value } => value,
-                                        }.min(90).max(10),
-                                    },
-                                }
-                            });
+    /// hands the input focus back to the root once there is nothing left to focus, otherwise it
+    /// stays on a window that no longer exists and keystrokes go nowhere
+    fn release_focus(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.root.set_input_focus(RevertTo::PointerRoot)?;
 
-                            screen.tile(padding, gaps)
-                        })?;
-                    }
-                },
-                NodeCommand::Reverse =>  {
-                    if let Some(focus) = self.focus.clone() {
-                        let padding = self.config.padding.clone();
-                        let gaps = self.config.gaps.clone();
+        if self.config.ewmh {
+            self.display
+                .use_ewmh(&self.root)
+                .set_active_window(0)?;
+        }
 
-                        self.focused(move |_, screen| {
-                            screen.map_internal(focus.id(), |mut left, mut right, insert| {
-                                right.reverse();
+        Ok(())
+    }
 
-                                left.reverse();
+    /// keeps the mirrored screen on the same desktop index as its source
+    fn sync_mirror(&mut self) {
+        if let Some((source, target)) = self.config.mirror {
+            if let Some(current) = self.screens.get(source).map(|screen| screen.current) {
+                if let Some(screen) = self.screens.get_mut(target) {
+                    screen.current = current.min(screen.desktops.len().saturating_sub(1));
 
-                                Node::Internal {
-                                    left: right,
-                                    right: left,
-                                    insert,
-                                }
-                            });
+                    screen.mark_dirty();
+                }
+            }
+        }
+    }
 
-                            screen.tile(padding, gaps)
-                        })?;
-                    }
-                },
-                NodeCommand::Kill => {
-                    self.map_focus(|focus| {
-                        focus.kill().map_err(|err| err.into())
-                    })?;
-                },
-                NodeCommand::Close => {
-                    let wm_protocols = self.atoms.wm_protocols.clone();
-                    let wm_delete = self.atoms.wm_delete.clone();
-
-                    self.map_focus(|focus| {
-                        focus.send_event(Event::ClientMessage {
-                            format: 32,
-                            window: focus.id(),
-                            type_: wm_protocols,
-                            data: ClientMessageData::Long([
-                                wm_delete.id(),
-                                0,
-                                0,
-                                0,
-                                0,
-                            ]),
-                        }, Vec::new(), false)?;
+    fn unmanage(&mut self, wid: u32) -> Result<(), Box<dyn std::error::Error>> {
+        // the terminal takes its slot back once the window it swallowed for is gone
+        if let Some(terminal) = self.swallowed.remove(&wid) {
+            self.all(|_, screen| {
+                if screen.replace(wid, terminal.clone()) {
+                    screen.mark_dirty();
+                }
 
-                        Ok(())
-                    })?;
-                },
-            },
-            Command::Desktop(desktop) => match desktop {
-                DesktopCommand::Focus { desktop } => {
-                    let padding = self.config.padding.clone();
-                    let gaps = self.config.gaps.clone();
-                    let ewmh = self.display.use_ewmh(&self.root);
+                Ok(())
+            })?;
+        }
 
-                    if self.config.desktops.pinned {
-                        self.focused(|index, screen| {
-                            screen.current = desktop.min(screen.desktops.len());
+        self.swallowed.retain(|_, terminal| terminal.id() != wid);
 
-                            ewmh.set_current_desktop((screen.current + screen.desktops.len() * index) as u32)?;
+        let removed = self.display.window_from_id(wid)?;
+        let home = self.screens.iter().position(|screen| screen.is_visible(&removed));
 
-                            screen.tile(padding, gaps)
-                        })?;
-                    } else {
-                        self.all(|index, screen| {
-                            if desktop > screen.desktops.len() * index {
-                                screen.current = (desktop - screen.desktops.len() * index).min(screen.desktops.len());
+        self.all(|_, screen| {
+            screen.forget(wid);
 
-                                ewmh.set_current_desktop((screen.current + screen.desktops.len() * index) as u32)?;
+            screen.mark_dirty();
 
-                                screen.tile(padding, gaps)?;
-                            }
+            Ok(())
+        })?;
 
-                            Ok(())
-                        })?;
-                    }
-                },
-            },
-            Command::Config(config) => match config {
-                ConfigCommand::Desktops { names, pinned } => {
-                    let length = names.len();
+        self.subscribers.emit(format!("unmap {:#x}", wid))?;
 
-                    self.config.desktops = crate::config::Desktops {
-                        names,
-                        pinned,
-                    };
+        self.fire(HookEvent::Remove, format!("{:#x}", wid))?;
 
-                    self.all(|_, screen| {
-                        screen.resize(length);
+        self.windows.remove(&wid);
 
-                        Ok(())
-                    })?;
+        self.marks.retain(|_, marked| *marked != wid);
 
-                    self.display
-                        .use_ewmh(&self.root)
-                        .set_number_of_desktops((length * self.screens.len()) as u32)?;
+        if self.anchor.as_ref().map(|anchor| anchor.id()) == Some(wid) {
+            self.anchor = None;
 
-                    self.update_viewport()?;
-                },
-                ConfigCommand::Window { gaps } => {
-                    let padding = self.config.padding.clone();
+            self.hide_presel()?;
+        }
 
-                    self.config.gaps = gaps;
+        self.history.retain(|focused| *focused != wid);
 
-                    self.all(|_, screen| screen.tile(padding, gaps))?;
-                },
-                ConfigCommand::Border { normal, focused, width } => {
-                    let padding = self.config.padding.clone();
-                    let gaps = self.config.gaps.clone();
+        if self.focus.as_ref().map(|window| window.id()) == Some(wid) {
+            self.focus = None;
 
-                    self.config.border = crate::config::Border {
-                        normal: u32::from_str_radix(&normal, 16)?,
-                        focused: u32::from_str_radix(&focused, 16)?,
-                        width,
-                    };
+            // x would revert the focus to the parent of the window, which is the root or some
+            // unmanaged window, so the focus is handed to a managed window on the same screen
+            match self.revert_target(home)? {
+                Some(next) => {
+                    let window = self.display.window_from_id(next)?;
 
-                    self.all(|_, screen| screen.tile(padding, gaps))?;
+                    self.set_focus(window)?;
                 },
-                ConfigCommand::Padding { top, bottom, left, right } => {
-                    self.config.padding = crate::config::Padding {
-                        top,
-                        bottom,
-                        left,
-                        right,
-                    };
+                None => self.release_focus()?,
+            }
+        }
 
-                    let padding = self.config.padding.clone();
-                    let gaps = self.config.gaps.clone();
+        Ok(())
+    }
 
-                    self.all(|_, screen| screen.tile(padding, gaps))?;
-                },
-                ConfigCommand::PointerFollowsFocus => self.config.pf.pointer_follows ^= true,
-                ConfigCommand::FocusFollowsPointer => self.config.pf.focus_follows ^= true,
-            },
-            Command::Exit => {
-                self.should_close = true;
-            },
+    /// honors a fullscreen request of the window and mirrors the state in its `_NET_WM_STATE`
+    fn set_fullscreen(&mut self, window: &Window, enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let changed = self.screens.iter_mut()
+            .find(|screen| screen.contains(window))
+            .map(|screen| {
+                let changed = screen.set_fullscreen(window, enabled);
+
+                if changed {
+                    screen.mark_dirty();
+                }
+
+                changed
+            })
+            .unwrap_or(false);
+
+        if !changed {
+            return Ok(());
         }
 
+        let fullscreen = self.atoms.net_wm_state_fullscreen.id();
+
+        let states = self.get_u32_property(window, "_NET_WM_STATE", Atom::ATOM)?
+            .into_iter()
+            .filter(|state| *state != fullscreen)
+            .chain(enabled.then_some(fullscreen))
+            .flat_map(|state| state.to_ne_bytes())
+            .collect::<Vec<u8>>();
+
+        window.change_property(self.atoms.net_wm_state.clone(), Atom::ATOM, PropFormat::Format32, PropMode::Replace, &states)?;
+
+        // the border comes back together with the slot in the tree
+        self.set_border(window, self.focus.as_ref() == Some(window))?;
+
+        self.subscribers.emit(format!("fullscreen {:#x} {}", window.id(), enabled))?;
+
         Ok(())
     }
 
-    pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let display = self.display.clone();
-        let events = self.events.clone();
+    /// the window focused most recently that is still visible on the screen, otherwise whatever
+    /// the current desktop of the screen would focus
+    fn revert_target(&mut self, screen: Option<usize>) -> Result<Option<u32>, Box<dyn std::error::Error>> {
+        let Some(screen) = screen.and_then(|index| self.screens.get(index)) else {
+            return self.focused(|_, screen| Ok(screen.focus_target()));
+        };
 
-        self.load_screens()?;
+        let visible = screen.desktops.get(screen.current)
+            .map(|desktop| desktop.windows().iter().map(|window| window.id()).collect::<HashSet<u32>>())
+            .unwrap_or_default();
 
-        self.set_supporting_ewmh()?;
+        let fallback = screen.focus_target().filter(|wid| self.windows.contains_key(wid));
 
-        server::spawn(events.clone());
+        Ok(revert_focus(&self.history, |wid| self.windows.contains_key(&wid) && visible.contains(&wid), fallback))
+    }
 
-        thread::spawn(move || {
-            listen(display, events).expect("failed to listen");
-        });
+    fn set_wm_state(&self, window: &Window, state: u32) -> Result<(), Box<dyn std::error::Error>> {
+        // the second field is the icon window which we never create
+        let data = [state, 0].iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect::<Vec<u8>>();
 
-        startup::startup()?;
+        window.change_property(self.atoms.wm_state.clone(), self.atoms.wm_state.clone(), PropFormat::Format32, PropMode::Replace, &data)?;
 
-        while !self.should_close {
-            match self.events.wait()? {
-                EventType::XEvent(event) => {
-                    self.handle_event(event)?;
-                },
-                EventType::Config(args) => {
-                    self.handle_config(args)?;
-                },
+        Ok(())
+    }
+
+    /// shows where the next window will be inserted next to the anchor or the focused window
+    fn show_presel(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.hide_presel()?;
+
+        if let Some(focus) = self.anchor.clone().or_else(|| self.focus.clone()) {
+            let padding = self.config.padding.clone();
+            let gaps = self.config.gaps.clone();
+            let insert = self.config.insert.clone();
+
+            let area = self.screens.iter().find_map(|screen| screen.area_of(focus.id(), padding, gaps));
+
+            if let Some(area) = area {
+                let (first, second) = Node::split(area, Split::from(insert.dir), insert.ratio);
+
+                let area = match insert.dir {
+                    Direction::East | Direction::South => second,
+                    Direction::West | Direction::North => first,
+                };
+
+                // override redirect keeps the overlay from ending up in our own MapRequest handler
+                let overlay = self.root.create_window(WindowArguments {
+                    depth: self.root.depth(),
+                    x: area.x,
+                    y: area.y,
+                    width: area.width.max(1),
+                    height: area.height.max(1),
+                    class: WindowClass::InputOutput,
+                    border_width: 0,
+                    visual: self.root.visual(),
+                    values: ValuesBuilder::new(vec![
+                        WindowValue::BackgroundPixel(self.config.presel),
+                        WindowValue::OverrideRedirect(true),
+                    ]),
+                })?;
+
+                overlay.map(WindowKind::Window)?;
+
+                overlay.raise()?;
+
+                self.presel = Some(overlay);
             }
         }
 
         Ok(())
     }
-}
 
-fn listen(display: Display, events: Arc<Queue<EventType>>) -> Result<(), Box<dyn std::error::Error>> {
-    loop {
-        let event = display.next_event()?;
+    fn hide_presel(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(overlay) = self.presel.take() {
+            overlay.destroy(WindowKind::Window)?;
+        }
 
-        events.push(EventType::XEvent(event))?;
+        Ok(())
     }
-}
 
+    /// remembers the windows that were unmapped while hiding desktops
+    fn collect_unmaps(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let unmapped = self.screens.iter_mut()
+            .flat_map(|screen| screen.unmapped.drain(..))
+            .collect::<Vec<u32>>();
+
+        for wid in unmapped {
+            *self.unmaps.entry(wid).or_default() += 1;
+
+            let window = self.display.window_from_id(wid)?;
+
+            self.set_wm_state(&window, ICONIC_STATE)?;
+        }
+
+        Ok(())
+    }
+
+    fn managed_windows(&self) -> Vec<Window> {
+        self.screens.iter()
+            .flat_map(|screen| screen.desktops.iter())
+            .flat_map(|desktop| desktop.windows())
+            .collect()
+    }
+
+    fn grab_buttons(&self, window: &Window) -> Result<(), Box<dyn std::error::Error>> {
+        let mouse = &self.config.mouse;
+
+        for button in [mouse.move_button, mouse.resize_button] {
+            window.grab_button(button, mouse.modifier, &[
+                EventMask::ButtonPress,
+                EventMask::ButtonRelease,
+                EventMask::PointerMotion,
+            ])?;
+        }
+
+        Ok(())
+    }
 
+    fn ungrab_buttons(&self, window: &Window) -> Result<(), Box<dyn std::error::Error>> {
+        let mouse = &self.config.mouse;
+
+        for button in [mouse.move_button, mouse.resize_button] {
+            window.ungrab_button(button, mouse.modifier)?;
+        }
+
+        Ok(())
+    }
+
+    fn is_managed(&self, window: &Window) -> bool {
+        self.screens.iter().any(|screen| screen.contains(window))
+    }
+
+    fn map_request(&mut self, wid: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let monitor = self.monitor;
+
+        // new windows open where the pointer is rather than next to the focus, unless an insert
+        // was anchored to a window
+        self.monitor = self.anchor_screen().or_else(|| self.pointer_screen());
+
+        let result = self.manage(wid);
+
+        self.monitor = monitor;
+
+        result
+    }
+
+    fn manage(&mut self, wid: u32) -> Result<(), Box<dyn std::error::Error>> {
+        self.hide_presel()?;
+
+        let window = self.display.window_from_id(wid)?;
+        let padding = self.config.padding.clone();
+        let gaps = self.config.gaps.clone();
+
+        // an anchor only applies to the next window, everything after it is inserted at the focus
+        let focus = self.anchor.take()
+            .filter(|anchor| self.is_managed(anchor))
+            .or_else(|| self.focus.clone());
+
+        let insert = match (self.config.insert_mode, &focus) {
+            (InsertMode::Automatic, Some(focus)) => self.focused(|_, screen| Ok(screen.area_of(focus.id(), padding, gaps)))?
+                .map(|area| self.config.insert.fit(area))
+                .unwrap_or(self.config.insert),
+            _ => self.config.insert.clone(),
+        };
+
+        window.select_input(&[
+            EventMask::SubstructureNotify,
+            EventMask::SubstructureRedirect,
+            EventMask::EnterWindow,
+            EventMask::FocusChange,
+            EventMask::PropertyChange,
+        ])?;
+
+        let borderless = !self.config.ignore_motif && self.is_undecorated(&window)?;
+
+        self.grab_buttons(&window)?;
+
+        let types = self.display
+            .use_ewmh(&window)
+            .get_wm_window_type()?;
+
+        let transient_for = self.get_window_property(&window, "WM_TRANSIENT_FOR")?
+            .map(|wid| self.display.window_from_id(wid))
+            .transpose()?
+            .filter(|parent| self.is_managed(parent));
+
+        let (instance, class) = self.get_class(&window)?;
+
+        let (skip_taskbar, skip_pager) = self.get_skip_state(&window)?;
+
+        let frame = self.get_frame_extents(&window)?;
+
+        let client = Client {
+            pid: self.display.use_ewmh(&window).get_wm_pid().ok(),
+            machine: self.get_string_property(&window, "WM_CLIENT_MACHINE")?,
+            transient_for: transient_for.as_ref().map(|parent| parent.id()),
+            instance,
+            class,
+            borderless,
+            skip_taskbar,
+            skip_pager,
+            frame,
+        };
+
+        self.windows.insert(window.id(), client);
+
+        if frame.is_some() || borderless {
+            self.sync_desktops();
+        }
+
+        // dialogs belong on top of their owner rather than in the tree
+        let state = match &transient_for {
+            Some(parent) => {
+                self.center_over(&window, parent)?;
+
+                State::Float
+            },
+            // undecorated windows are mostly splash screens and games that size themselves
+            None => match State::from(&types) {
+                State::Tiled if borderless => State::Float,
+                state => state,
+            },
+        };
+
+        let state = match (state, self.config.default_state) {
+            (State::Dock, _) | (_, None) => state,
+            (_, Some(default)) => default,
+        };
+
+        let swallower = match state == State::Tiled && self.config.swallow {
+            true => self.swallower(window.id()),
+            false => None,
+        };
+
+        match swallower {
+            Some(terminal) => {
+                self.all(|_, screen| {
+                    if screen.replace(terminal.id(), window.clone()) {
+                        screen.unmapped.push(terminal.id());
+
+                        screen.mark_dirty();
+                    }
+
+                    Ok(())
+                })?;
+
+                terminal.unmap(WindowKind::Window)?;
+
+                self.swallowed.insert(window.id(), terminal);
+            },
+            None => self.focused(|_, screen| {
+                screen.insert(
+                    window.clone(),
+                    insert.clone(),
+                    focus.clone()
+                        .map(|focus| Point::Window(focus))
+                        .unwrap_or(Point::Any),
+                    state,
+                );
+
+                screen.mark_dirty();
+
+                Ok(())
+            })?,
+        }
+
+        self.set_border(&window, false)?;
+
+        // a user time of zero means the client doesn't want to be focused when mapped
+        let user_time = self.get_u32_property(&window, "_NET_WM_USER_TIME", Atom::CARDINAL)?;
+
+        if self.config.focus_on_map && state != State::Dock && user_time.first() != Some(&0) {
+            self.pending_focus = Some(window.id());
+        }
+
+        self.subscribers.emit(format!("map {:#x}", window.id()))?;
+
+        self.fire(HookEvent::Add, format!("{:#x}", window.id()))?;
+
+        if skip_taskbar || skip_pager {
+            self.subscribers.emit(format!("skip {:#x} {} {}", window.id(), skip_taskbar, skip_pager))?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_hover(&mut self, wid: u32) -> Result<(), Box<dyn std::error::Error>> {
+        if self.hover != Some(wid) || !self.windows.contains_key(&wid) {
+            return Ok(());
+        }
+
+        self.hover = None;
+
+        let window = self.display.window_from_id(wid)?;
+        let geometry = window.get_geometry()?;
+        let pointer = self.root.query_pointer()?;
+
+        if Area::new(geometry.x, geometry.y, geometry.width, geometry.height).contains(pointer.root_x, pointer.root_y) {
+            self.give_focus(&window)?;
+        }
+
+        Ok(())
+    }
+
+    fn schedule_frame(&self, id: u64) {
+        let events = self.events.clone();
+
+        thread::spawn(move || {
+            thread::sleep(FRAME_INTERVAL);
+
+            let _ = events.push(EventType::Frame(id));
+        });
+    }
+
+    /// starts interpolating the ratio of the split, replacing any animation that is still running
+    fn animate(&mut self, screen: usize, desktop: usize, wid: u32, level: usize, from: i8, to: i8, duration: u16) {
+        self.frames += 1;
+
+        self.animation = Some(Animation {
+            id: self.frames,
+            screen,
+            desktop,
+            wid,
+            level,
+            from,
+            to,
+            started: Instant::now(),
+            duration: Duration::from_millis(duration as u64),
+        });
+
+        self.schedule_frame(self.frames);
+    }
+
+    fn handle_frame(&mut self, id: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(animation) = self.animation.as_ref().filter(|animation| animation.id == id) else {
+            return Ok(());
+        };
+
+        let ratio = animation.ratio();
+        let done = ratio == animation.to;
+        let (index, desktop, wid, level, to) = (animation.screen, animation.desktop, animation.wid, animation.level, animation.to);
+
+        if let Some(screen) = self.screens.get_mut(index) {
+            // the desktop may have been switched away from, it just gets the final ratio then
+            if screen.current != desktop {
+                if let Some(desktop) = screen.desktops.get_mut(desktop) {
+                    desktop.map_internal(wid, level, |left, right, split, _| Node::Internal { left, right, split, ratio: to });
+                }
+
+                self.animation = None;
+
+                return Ok(());
+            }
+
+            screen.map_internal(wid, level, |left, right, split, _| Node::Internal { left, right, split, ratio });
+
+            screen.mark_dirty();
+        }
+
+        match done {
+            true => self.animation = None,
+            false => self.schedule_frame(id),
+        }
+
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<(), Box<dyn std::error::Error>> {
+        println!("event: {:?}", event);
+
+        // focus changes that come from a command have no event of their own, so they are
+        // timestamped with the last event that had one
+        if let Event::ButtonPress { time, .. }
+            | Event::ButtonRelease { time, .. }
+            | Event::MotionNotify { time, .. }
+            | Event::EnterNotify { time, .. }
+            | Event::PropertyNotify { time, .. } = event {
+            self.time = time;
+        }
+
+        match event {
+            Event::MapRequest { window, .. } if self.frozen => self.deferred.push(window),
+            Event::MapRequest { window, .. } => self.map_request(window)?,
+            Event::MapNotify { window, .. } if self.windows.contains_key(&window) => {
+                let wid = window;
+                let window = self.display.window_from_id(wid)?;
+
+                self.set_wm_state(&window, NORMAL_STATE)?;
+
+                if self.pending_focus == Some(wid) {
+                    self.pending_focus = None;
+
+                    self.set_focus(window)?;
+                }
+            },
+            Event::UnmapNotify { window, send_event, .. } => {
+                // icccm clients withdraw a window that is already unmapped, e.g. on a hidden
+                // desktop, by sending a synthetic unmap, so it can't be one of ours
+                let withdrawn = match send_event {
+                    true => {
+                        self.unmaps.remove(&window);
+
+                        true
+                    },
+                    // unmaps we caused ourselves by hiding a desktop don't mean the window is gone
+                    false => match self.unmaps.get_mut(&window) {
+                        Some(count) if *count > 1 => {
+                            *count -= 1;
+
+                            false
+                        },
+                        Some(_) => {
+                            self.unmaps.remove(&window);
+
+                            false
+                        },
+                        None => true,
+                    },
+                };
+
+                // a window asked to be mapped while frozen isn't managed yet, it just must not come back on thaw
+                if withdrawn {
+                    self.deferred.retain(|wid| *wid != window);
+                }
+
+                // menus, tooltips and other windows we never managed come and go all the time
+                if withdrawn && self.windows.contains_key(&window) {
+                    // the client withdrew the window so it is no longer in any icccm state
+                    self.display
+                        .window_from_id(window)?
+                        .delete_property(self.atoms.wm_state.clone())
+                        .ok();
+
+                    self.unmanage(window)?;
+                }
+            },
+            Event::DestroyNotify { window, .. } => {
+                self.unmaps.remove(&window);
+
+                self.deferred.retain(|wid| *wid != window);
+
+                if self.windows.contains_key(&window) {
+                    self.unmanage(window)?;
+                }
+            },
+            Event::ClientMessage { window, type_, data: ClientMessageData::Long(data), .. } if type_.id() == self.atoms.net_wm_state.id() && self.windows.contains_key(&window) => {
+                let fullscreen = self.atoms.net_wm_state_fullscreen.id();
+
+                if self.config.honor_fullscreen && (data[1] == fullscreen || data[2] == fullscreen) {
+                    let window = self.display.window_from_id(window)?;
+                    let current = self.screens.iter().any(|screen| screen.is_fullscreen(&window));
+
+                    // the action is one of remove, add and toggle
+                    let enabled = match data[0] {
+                        0 => false,
+                        1 => true,
+                        _ => !current,
+                    };
+
+                    self.set_fullscreen(&window, enabled)?;
+                }
+            },
+            Event::PropertyNotify { window, atom, .. } if atom == self.atoms.net_wm_state.id() && self.windows.contains_key(&window) => {
+                let skip = self.get_skip_state(&self.display.window_from_id(window)?)?;
+
+                if let Some(client) = self.windows.get_mut(&window) {
+                    if (client.skip_taskbar, client.skip_pager) != skip {
+                        (client.skip_taskbar, client.skip_pager) = skip;
+
+                        self.subscribers.emit(format!("skip {:#x} {} {}", window, skip.0, skip.1))?;
+                    }
+                }
+            },
+            Event::PropertyNotify { window, atom, .. } if atom == self.atoms.gtk_frame_extents.id() && self.windows.contains_key(&window) => {
+                let frame = self.get_frame_extents(&self.display.window_from_id(window)?)?;
+
+                if let Some(client) = self.windows.get_mut(&window).filter(|client| client.frame != frame) {
+                    client.frame = frame;
+
+                    self.sync_desktops();
+                }
+            },
+            Event::MappingNotify { .. } => {
+                // the modifier of the grabs resolves to different keys after e.g. `setxkbmap`, so
+                // the grabs are made again against the new mapping, keys are grabbed by the
+                // hotkey daemon and don't concern us
+                for window in self.managed_windows() {
+                    self.ungrab_buttons(&window)?;
+
+                    self.grab_buttons(&window)?;
+                }
+            },
+            Event::EnterNotify { window, root_x, root_y, .. } => {
+                let window = self.display.window_from_id(window)?;
+
+                // crossing into a window or the bare root is the only pointer motion we get to see
+                if let Some(index) = self.screens.iter().position(|screen| screen.area.contains(root_x, root_y)) {
+                    self.active_screen = Some(index);
+                }
+
+                if self.is_managed(&window) && self.config.pf.focus_follows {
+                    match self.config.pf.delay {
+                        0 => self.give_focus(&window)?,
+                        delay => {
+                            let events = self.events.clone();
+                            let wid = window.id();
+
+                            // only the window entered last may take the focus once its timer runs out
+                            self.hover = Some(wid);
+
+                            thread::spawn(move || {
+                                thread::sleep(Duration::from_millis(delay));
+
+                                let _ = events.push(EventType::Hover(wid));
+                            });
+                        },
+                    }
+                }
+            },
+            Event::ConfigureRequest { window, x, y, width, height, value_mask, stack_mode, .. } => {
+                let window = self.display.window_from_id(window)?;
+
+                let managed = self.is_managed(&window);
+                let floating = self.screens.iter().any(|screen| screen.is_floating(&window));
+
+                let geometry = window.get_geometry()?;
+
+                // the tiler owns the geometry and the stacking of tiled windows, so only floating
+                // and not yet managed windows get what they ask for
+                if !managed || floating {
+                    let area = requested_area(value_mask, x, y, width, height, Area::new(geometry.x, geometry.y, geometry.width, geometry.height));
+
+                    window.mov_resize(area.x, area.y, area.width, area.height)?;
+
+                    if managed && honors_restack(floating, value_mask, stack_mode) {
+                        window.raise()?;
+                    }
+                } else {
+                    // icccm wants a refused request answered with a synthetic notify of where the
+                    // window really is, some clients wait for it before they draw
+                    window.send_event(Event::ConfigureNotify {
+                        event: window.id(),
+                        window: window.id(),
+                        above_sibling: 0,
+                        x: geometry.x as i16,
+                        y: geometry.y as i16,
+                        width: geometry.width,
+                        height: geometry.height,
+                        border_width: geometry.border_width,
+                        override_redirect: false,
+                    }, vec![EventMask::StructureNotify], false)?;
+                }
+            },
+            Event::ConfigureNotify { window, .. } if window == self.root.id() => {
+                self.reconcile_screens()?;
+            },
+            Event::ButtonPress { window, button, root_x, root_y, .. } => {
+                let window = self.display.window_from_id(window)?;
+                let floating = self.screens.iter().any(|screen| screen.is_floating(&window));
+
+                // tiled windows are floated for the duration of the drag and put back on release
+                let retile = !floating
+                    && self.config.drag_to_retile
+                    && button == self.config.mouse.move_button
+                    && self.is_managed(&window);
+
+                if retile {
+                    let insert = self.config.insert.clone();
+
+                    self.focused(|_, screen| {
+                        screen.remove(window.id());
+
+                        screen.insert(window.clone(), insert, Point::Any, State::Float);
+
+                        screen.mark_dirty();
+
+                        Ok(())
+                    })?;
+                }
+
+                if floating || retile {
+                    let geometry = window.get_geometry()?;
+
+                    window.raise()?;
+
+                    self.drag = Some(Drag {
+                        window,
+                        button,
+                        pointer: (root_x, root_y),
+                        area: Area::new(geometry.x, geometry.y, geometry.width, geometry.height),
+                        retile,
+                    });
+                }
+            },
+            Event::MotionNotify { root_x, root_y, .. } => {
+                if let Some(drag) = &self.drag {
+                    let dx = root_x as i32 - drag.pointer.0 as i32;
+                    let dy = root_y as i32 - drag.pointer.1 as i32;
+
+                    if drag.button == self.config.mouse.move_button {
+                        drag.window.mov((drag.area.x as i32 + dx).max(0) as u16, (drag.area.y as i32 + dy).max(0) as u16)?;
+                    } else if drag.button == self.config.mouse.resize_button {
+                        drag.window.resize((drag.area.width as i32 + dx).max(1) as u16, (drag.area.height as i32 + dy).max(1) as u16)?;
+                    }
+                }
+            },
+            Event::ButtonRelease { .. } => {
+                if let Some(drag) = self.drag.take().filter(|drag| drag.retile) {
+                    let pointer = self.root.query_pointer()?;
+                    let insert = self.config.insert.clone();
+                    let padding = self.config.padding.clone();
+                    let gaps = self.config.gaps.clone();
+
+                    self.focused(|_, screen| {
+                        let target = screen.leaf_at(pointer.root_x, pointer.root_y, padding, gaps);
+
+                        screen.remove(drag.window.id());
+
+                        match target {
+                            Some((leaf, area)) => {
+                                let dir = area.nearest_edge(pointer.root_x, pointer.root_y);
+
+                                screen.insert(drag.window.clone(), Insert::new(dir, insert.ratio), Point::Window(leaf), State::Tiled);
+                            },
+                            None => screen.insert(drag.window.clone(), insert, Point::Any, State::Tiled),
+                        }
+
+                        screen.mark_dirty();
+
+                        Ok(())
+                    })?;
+
+                    self.set_border(&drag.window, self.focus.as_ref() == Some(&drag.window))?;
+                }
+            },
+            Event::FocusIn { window, .. } => {
+                let window = self.display.window_from_id(window)?;
+
+                if self.is_managed(&window) {
+                    self.set_border(&window, true)?;
+
+                    self.grabbed = grab_after_focus(self.grabbed, window.id());
+
+                    self.all(|_, screen| {
+                        screen.remember_focus(&window);
+
+                        Ok(())
+                    })?;
+
+                    self.subscribers.emit(format!("focus {:#x}", window.id()))?;
+
+                    self.fire(HookEvent::Focus, format!("{:#x}", window.id()))?;
+
+                    if self.config.ewmh {
+                        self.display
+                            .use_ewmh(&self.root)
+                            .set_active_window(window.id())?;
+                    }
+
+                    self.history.retain(|focused| *focused != window.id());
+
+                    self.history.push(window.id());
+
+                    for (wid, _) in self.windows.iter().filter(|(_, client)| client.transient_for == Some(window.id())) {
+                        self.display.window_from_id(*wid)?.raise()?;
+                    }
+
+                    if let Some(focus) = self.focus.replace(window.clone()) {
+                        if focus.id() != window.id() {
+                            self.set_border(&focus, false)?;
+
+                            self.level = 0;
+                        }
+                    }
+                }
+            },
+            _ => {},
+        }
+
+        Ok(())
+    }
+
+    fn handle_query(&mut self, query: QueryCommand) -> Result<Response, Box<dyn std::error::Error>> {
+        let focus = self.focus.as_ref().map(|focus| focus.id());
+        let windows = self.windows.clone();
+
+        match query {
+            QueryCommand::Tree => {
+                let query = self.focused(|index, screen| {
+                    Ok(Some(DesktopQuery {
+                        screen: index,
+                        desktop: screen.current,
+                        focus,
+                        tree: screen.desktops.get(screen.current).and_then(|desktop| desktop.tree(&windows)),
+                    }))
+                })?;
+
+                Ok(Response::Message(serde_json::to_string(&query)?))
+            },
+            QueryCommand::Report => Ok(Response::Message(self.report())),
+            QueryCommand::Monitors => {
+                let sizes = self.desktop_sizes();
+
+                let monitors = self.screens.iter()
+                    .enumerate()
+                    .map(|(index, screen)| MonitorQuery {
+                        name: index.to_string(),
+                        area: screen.area,
+                        current: desktop::global_index(&sizes, index, screen.current),
+                        desktops: (0..screen.desktops.len())
+                            .map(|desktop| self.config.desktops.names.get(desktop).cloned().unwrap_or_else(|| (desktop + 1).to_string()))
+                            .collect(),
+                    })
+                    .collect::<Vec<MonitorQuery>>();
+
+                Ok(Response::Message(serde_json::to_string(&monitors)?))
+            },
+            QueryCommand::Ascii => {
+                let tree = self.focused(|_, screen| {
+                    Ok(screen.desktops.get(screen.current).and_then(|desktop| desktop.clients.clone()))
+                })?;
+
+                let diagram = tree
+                    .map(|tree| tree.render_ascii(&|window| self.title(window)))
+                    .unwrap_or_else(|| String::from("empty"));
+
+                Ok(Response::Message(diagram))
+            },
+        }
+    }
+
+    /// formats the state of every screen the way bspwm reports it, e.g. `WM0:Oone:ftwo:LT:TT:G`
+    fn report(&self) -> String {
+        let active = self.focus.as_ref()
+            .and_then(|focus| self.screens.iter().position(|screen| screen.contains(focus)))
+            .unwrap_or_default();
+
+        let mut items = Vec::new();
+
+        for (index, screen) in self.screens.iter().enumerate() {
+            items.push(format!("{}{index}", if index == active { 'M' } else { 'm' }));
+
+            for (desktop, state) in screen.desktops.iter().enumerate() {
+                let name = self.config.desktops.names.get(desktop)
+                    .cloned()
+                    .unwrap_or_else(|| (desktop + 1).to_string());
+
+                let flag = match (state.windows().is_empty(), desktop == screen.current) {
+                    (false, true) => 'O',
+                    (false, false) => 'o',
+                    (true, true) => 'F',
+                    (true, false) => 'f',
+                };
+
+                items.push(format!("{flag}{name}"));
+            }
+
+            if index == active {
+                let state = match self.focus.as_ref().map(|focus| screen.is_floating(focus)) {
+                    Some(true) => "TF",
+                    Some(false) => "TT",
+                    None => "T",
+                };
+
+                items.extend(["LT".to_string(), state.to_string(), "G".to_string()]);
+            }
+        }
+
+        format!("W{}", items.join(":"))
+    }
+
+    fn get_string_property(&self, window: &Window, name: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let property = self.display.intern_atom(name, false)?;
+
+        Ok(window.get_property(property, Atom::STRING, false)
+            .ok()
+            .filter(|data| !data.is_empty())
+            .map(|data| String::from_utf8_lossy(&data).trim_end_matches('\0').to_string()))
+    }
+
+    fn get_u32_property(&self, window: &Window, name: &str, type_: Atom) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+        let property = self.display.intern_atom(name, false)?;
+
+        Ok(window.get_property(property, type_, false)
+            .map(|data| {
+                data.chunks_exact(4)
+                    .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// whether `_NET_WM_STATE` asks to keep the window out of the taskbar and the pager
+    fn get_skip_state(&self, window: &Window) -> Result<(bool, bool), Box<dyn std::error::Error>> {
+        let states = self.get_u32_property(window, "_NET_WM_STATE", Atom::ATOM)?;
+
+        Ok((states.contains(&self.atoms.skip_taskbar.id()), states.contains(&self.atoms.skip_pager.id())))
+    }
+
+    /// the `_GTK_FRAME_EXTENTS` of the window, which come as left, right, top and bottom
+    fn get_frame_extents(&self, window: &Window) -> Result<Option<Padding>, Box<dyn std::error::Error>> {
+        Ok(match self.get_u32_property(window, "_GTK_FRAME_EXTENTS", Atom::CARDINAL)?.as_slice() {
+            [left, right, top, bottom, ..] => Some(Padding {
+                top: *top as u16,
+                bottom: *bottom as u16,
+                left: *left as u16,
+                right: *right as u16,
+            }),
+            _ => None,
+        })
+    }
+
+    fn get_window_property(&self, window: &Window, name: &str) -> Result<Option<u32>, Box<dyn std::error::Error>> {
+        Ok(self.get_u32_property(window, name, Atom::WINDOW)?
+            .first()
+            .copied()
+            .filter(|wid| *wid != 0))
+    }
+
+    /// the command line of the window from `WM_COMMAND`, or from the process behind `_NET_WM_PID`
+    fn get_command(&self, window: &Window) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let split = |command: &str| command.split('\0')
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| arg.to_string())
+            .collect::<Vec<String>>();
+
+        if let Some(command) = self.get_string_property(window, "WM_COMMAND")? {
+            return Ok(split(&command));
+        }
+
+        Ok(match self.get_u32_property(window, "_NET_WM_PID", Atom::CARDINAL)?.first() {
+            Some(pid) => fs::read(format!("/proc/{pid}/cmdline"))
+                .map(|cmdline| split(&String::from_utf8_lossy(&cmdline)))
+                .unwrap_or_default(),
+            None => Vec::new(),
+        })
+    }
+
+    /// splits `WM_CLASS` into its instance and class names
+    fn get_class(&self, window: &Window) -> Result<(Option<String>, Option<String>), Box<dyn std::error::Error>> {
+        let class = self.get_string_property(window, "WM_CLASS")?.unwrap_or_default();
+
+        let mut names = class.split('\0')
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string());
+
+        Ok((names.next(), names.next()))
+    }
+
+    /// whether `_MOTIF_WM_HINTS` asks for the window to be undecorated
+    fn is_undecorated(&self, window: &Window) -> Result<bool, Box<dyn std::error::Error>> {
+        let atom = self.display.intern_atom("_MOTIF_WM_HINTS", false)?;
+        let hints = self.get_u32_property(window, "_MOTIF_WM_HINTS", atom)?;
+
+        Ok(matches!(hints.as_slice(), [flags, _, decorations, ..] if flags & 0x2 != 0 && *decorations == 0))
+    }
+
+    fn center_over(&self, window: &Window, parent: &Window) -> Result<(), Box<dyn std::error::Error>> {
+        let parent = parent.get_geometry()?;
+
+        self.center_in(window, Area::new(parent.x, parent.y, parent.width, parent.height))
+    }
+
+    /// moves the window to the middle of the area without resizing it
+    fn center_in(&self, window: &Window, area: Area) -> Result<(), Box<dyn std::error::Error>> {
+        let geometry = window.get_geometry()?;
+
+        let (x, y) = centered(area, geometry.width, geometry.height);
+
+        window.mov(x, y)?;
+
+        Ok(())
+    }
+
+    fn fire(&self, event: HookEvent, argument: String) -> Result<(), Box<dyn std::error::Error>> {
+        for (_, command) in self.config.hooks.iter().filter(|(hook, _)| *hook == event) {
+            spawn(command, &[argument.clone()])?;
+        }
+
+        Ok(())
+    }
+
+    /// the screen and the desktop holding the window, whether that desktop is shown or not
+    fn locate(&self, window: &Window) -> Option<(usize, usize)> {
+        self.screens.iter().enumerate().find_map(|(index, screen)| {
+            screen.desktops.iter()
+                .position(|desktop| desktop.contains(window))
+                .map(|desktop| (index, desktop))
+        })
+    }
+
+    /// runs `f` on the screen and the desktop that hold the window rather than the focused ones,
+    /// the screen is retiled once the queue drained, `None` means no desktop holds the window
+    fn holding<F, R>(&mut self, window: &Window, f: F) -> Option<R>
+    where
+        F: FnOnce(usize, &mut Screen, usize) -> R,
+    {
+        let (index, desktop) = self.locate(window)?;
+        let screen = self.screens.get_mut(index)?;
+
+        let result = f(index, screen, desktop);
+
+        screen.mark_dirty();
+
+        Some(result)
+    }
+
+    /// the screen showing the window the next insert is anchored to
+    fn anchor_screen(&self) -> Option<usize> {
+        let anchor = self.anchor.as_ref()?;
+
+        self.screens.iter().position(|screen| screen.is_visible(anchor))
+    }
+
+    /// the screen the pointer last entered, if the config wants commands to follow the pointer
+    fn pointer_screen(&self) -> Option<usize> {
+        self.active_screen.filter(|_| self.config.pointer_monitor)
+    }
+
+    /// the window directional focus moves to, see `WrapMode` for what happens at the edge
+    fn neighbour(&self, from: &Window, dir: Direction) -> Result<Option<Window>, Box<dyn std::error::Error>> {
+        let area_of = |window: &Window| -> Result<Area, Box<dyn std::error::Error>> {
+            let geometry = window.get_geometry()?;
+
+            Ok(Area::new(geometry.x, geometry.y, geometry.width, geometry.height))
+        };
+
+        let Some(index) = self.screens.iter().position(|screen| screen.is_visible(from)) else {
+            return Ok(None);
+        };
+
+        let origin = area_of(from)?;
+
+        let visible = |screen: &Screen| screen.desktops.get(screen.current)
+            .map(|desktop| desktop.windows())
+            .unwrap_or_default();
+
+        let mut candidates = Vec::new();
+
+        for window in visible(&self.screens[index]).into_iter().filter(|window| window != from) {
+            let area = area_of(&window)?;
+
+            candidates.push((window, area));
+        }
+
+        let nearest = |candidates: &[(Window, Area)], origin: &Area| candidates.iter()
+            .filter_map(|(window, area)| origin.distance_toward(area, dir).map(|distance| (distance, window)))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, window)| window.clone());
+
+        if let Some(window) = nearest(&candidates, &origin) {
+            return Ok(Some(window));
+        }
+
+        let mode = self.config.focus_wrap.unwrap_or(match self.screens.len() > 1 {
+            true => WrapMode::Monitor,
+            false => WrapMode::Stop,
+        });
+
+        let opposite = match dir {
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+        };
+
+        match mode {
+            WrapMode::Stop => Ok(None),
+            // the window furthest away on the other side is where the focus comes back in
+            WrapMode::Wrap => Ok(candidates.iter()
+                .filter_map(|(window, area)| origin.distance_toward(area, opposite).map(|distance| (distance, window)))
+                .max_by_key(|(distance, _)| *distance)
+                .map(|(_, window)| window.clone())),
+            WrapMode::Monitor => {
+                let screen = self.screens.iter()
+                    .filter_map(|screen| self.screens[index].area.distance_toward(&screen.area, dir).map(|distance| (distance, screen)))
+                    .min_by_key(|(distance, _)| *distance)
+                    .map(|(_, screen)| screen);
+
+                screen.and_then(|screen| screen.focus_target())
+                    .map(|wid| self.display.window_from_id(wid))
+                    .transpose()
+                    .map_err(Into::into)
+            },
+        }
+    }
+
+    /// switches to the desktop of the window and focuses it
+    fn summon(&mut self, window: Window) -> Result<(), Box<dyn std::error::Error>> {
+        let switched = self.reveal(&window)?;
+
+        if self.screens.iter().any(|screen| screen.is_floating(&window)) {
+            window.raise()?;
+        }
+
+        self.focus_shown(window, switched)
+    }
+
+    fn warp(&self, window: &Window) -> Result<(), Box<dyn std::error::Error>> {
+        let geometry = window.get_geometry()?;
+
+        // the destination is relative to the window so the center doesn't depend on the screen
+        window.warp_pointer((geometry.width / 2) as i16, (geometry.height / 2) as i16)?;
+
+        Ok(())
+    }
+
+    fn title(&self, window: &Window) -> String {
+        self.display
+            .use_ewmh(window)
+            .get_wm_name()
+            .unwrap_or_default()
+    }
+
+    /// the layout, restacking, ratio bounds, frame extents and borderless windows are global so
+    /// every desktop, including ones created later, follows them
+    fn sync_desktops(&mut self) {
+        let layout = self.config.layout;
+        let lazy_restack = self.config.lazy_restack;
+        let bounds = self.config.bounds;
+
+        let frames = self.windows.iter()
+            .filter_map(|(wid, client)| client.frame.map(|frame| (*wid, frame)))
+            .collect::<HashMap<u32, Padding>>();
+
+        let borderless = self.windows.iter()
+            .filter(|(_, client)| client.borderless)
+            .map(|(wid, _)| *wid)
+            .collect::<HashSet<u32>>();
+
+        for screen in self.screens.iter_mut() {
+            for desktop in screen.desktops.iter_mut() {
+                desktop.layout = layout;
+                desktop.lazy_restack = lazy_restack;
+                desktop.bounds = bounds;
+                desktop.frames = frames.clone();
+                desktop.borderless = borderless.clone();
+            }
+
+            screen.mark_dirty();
+        }
+    }
+
+    fn desktop_sizes(&self) -> Vec<usize> {
+        self.screens.iter().map(|screen| screen.desktops.len()).collect()
+    }
+
+    /// exchanges two desktops given as `(screen, local)`, both screens are retiled afterwards
+    fn swap_desktops(&mut self, a: (usize, usize), b: (usize, usize)) {
+        let area = self.screens[a.0].area;
+
+        let first = std::mem::replace(&mut self.screens[a.0].desktops[a.1], Desktop::new(area));
+        let second = std::mem::replace(&mut self.screens[b.0].desktops[b.1], first);
+
+        self.screens[a.0].desktops[a.1] = second;
+
+        for index in [a.0, b.0] {
+            let screen = &mut self.screens[index];
+
+            screen.set_area(screen.area);
+
+            screen.mark_dirty();
+        }
+    }
+
+    /// switches every screen to the desktop the window is on, returns whether any had to switch
+    fn reveal(&mut self, window: &Window) -> Result<bool, Box<dyn std::error::Error>> {
+        let sizes = self.desktop_sizes();
+        let ewmh = self.config.ewmh.then(|| self.display.use_ewmh(&self.root));
+
+        let mut switched = false;
+
+        self.all(|index, screen| {
+            match screen.desktops.iter().position(|desktop| desktop.contains(window)) {
+                Some(desktop) if desktop != screen.current => {
+                    screen.current = desktop;
+
+                    switched = true;
+
+                    if let Some(ewmh) = &ewmh {
+                        ewmh.set_current_desktop(desktop::global_index(&sizes, index, screen.current) as u32)?;
+                    }
+
+                    screen.mark_dirty();
+
+                    Ok(())
+                },
+                _ => Ok(()),
+            }
+        })?;
+
+        self.sync_mirror();
+
+        Ok(switched)
+    }
+
+    /// focuses a window that may sit on a desktop that was just switched to, its windows are
+    /// only mapped once the retile is flushed and while frozen that waits until the thaw
+    fn focus_shown(&mut self, window: Window, switched: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.flush_tiles()?;
+
+        match self.frozen && switched {
+            true => self.pending_focus = Some(window.id()),
+            false => self.set_focus(window)?,
+        }
+
+        Ok(())
+    }
+
+    fn layout_path(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        if name.is_empty() || name.contains('/') {
+            return Err(format!("invalid layout name: {name:?}").into());
+        }
+
+        Ok(PathBuf::from(std::env::var("HOME")?).join(".config/yokai/layouts").join(format!("{name}.json")))
+    }
+
+    fn dump(&self, path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        let windows = self.managed_windows()
+            .into_iter()
+            .map(|window| {
+                let client = self.windows.get(&window.id()).cloned().unwrap_or_default();
+
+                WindowInfo {
+                    id: window.id(),
+                    title: self.title(&window),
+                    pid: client.pid,
+                    skip_taskbar: client.skip_taskbar,
+                    skip_pager: client.skip_pager,
+                }
+            })
+            .collect();
+
+        let dump = Dump {
+            version: ipc::VERSION,
+            focus: self.focus.as_ref().map(|focus| focus.id()),
+            screens: self.screens.iter().map(|screen| screen.dump(&self.config.desktops.names, &self.windows)).collect(),
+            windows,
+            config: self.config.clone(),
+        };
+
+        let json = serde_json::to_string_pretty(&dump)?;
+
+        // written before replying so the client learns about a dump that couldn't be written
+        fs::write(&path, json).map_err(|err| format!("failed to write dump to {}: {}", path.display(), err))?;
+
+        Ok(())
+    }
+
+    /// resolves the window a node command operates on
+    fn select(&self, selector: &Selector) -> Result<Option<Window>, Box<dyn std::error::Error>> {
+        let window = match selector.descriptor {
+            Descriptor::Marked => selector.mark.as_ref()
+                .and_then(|name| self.marks.get(name))
+                .map(|wid| self.display.window_from_id(*wid))
+                .transpose()?,
+            _ => self.focus.clone(),
+        };
+
+        Ok(match selector.path.is_empty() {
+            true => window,
+            false => window.and_then(|window| {
+                self.screens.iter()
+                    .flat_map(|screen| screen.desktops.iter())
+                    .find(|desktop| desktop.contains(&window))
+                    .and_then(|desktop| desktop.jump(&window, &selector.path))
+            }),
+        })
+    }
+
+    /// every window the selector matches, the modifier filters the managed windows
+    fn select_all(&self, selector: &Selector) -> Result<Vec<Window>, Box<dyn std::error::Error>> {
+        // these relate two windows to each other, there is nothing to relate every window to
+        if matches!(selector.modifier, Modifier::DescententOf | Modifier::AncestorOf) {
+            return Err(format!("--all doesn't support the {:?} modifier", selector.modifier).to_lowercase().into());
+        }
+
+        let marked = selector.mark.as_ref().and_then(|name| self.marks.get(name));
+        let mut windows = Vec::new();
+
+        for screen in self.screens.iter() {
+            for (index, desktop) in screen.desktops.iter().enumerate() {
+                for window in desktop.windows() {
+                    let matches = match selector.modifier {
+                        Modifier::Focused => self.focus.as_ref() == Some(&window),
+                        Modifier::Active | Modifier::Local => index == screen.current,
+                        Modifier::Leaf | Modifier::Tiled => !desktop.is_floating(&window),
+                        Modifier::Floating => desktop.is_floating(&window),
+                        Modifier::Fullscreen => desktop.fullscreen.as_ref() == Some(&window),
+                        Modifier::DescententOf | Modifier::AncestorOf => false,
+                    };
+
+                    let matches = match selector.descriptor {
+                        Descriptor::Marked => matches && marked == Some(&window.id()),
+                        _ => matches,
+                    };
+
+                    if matches {
+                        windows.push(window);
+                    }
+                }
+            }
+        }
+
+        Ok(windows)
+    }
+
+    fn handle_node(&mut self, node: NodeCommand, target: Option<Window>) -> Result<(), Box<dyn std::error::Error>> {
+        match node {
+            NodeCommand::Insert { dir, ratio, toggle, anchor } => {
+                let bounds = self.config.bounds;
+
+                let ratio = ratio.map(|ratio| bounds.check(ratio))
+                    .transpose()?
+                    .unwrap_or(self.config.insert.ratio);
+
+                let insert = Insert::new(dir, ratio);
+
+                self.config.insert = (insert == self.config.insert && toggle)
+                    .then(|| Insert::default())
+                    .unwrap_or(insert);
+
+                self.anchor = match anchor {
+                    true => Some(target.clone().ok_or("the selector doesn't match a window to anchor the insert to")?),
+                    false => None,
+                };
+
+                match self.config.insert == Insert::default() && self.anchor.is_none() {
+                    true => self.hide_presel()?,
+                    false => self.show_presel()?,
+                }
+            },
+            NodeCommand::Move { dx, dy } => {
+                // TODO: support negative numbers
+                if let Some(focus) = target.clone() {
+                    let geometry = focus.get_geometry()?;
+
+                    focus.mov((geometry.x as i32 + dx).max(0) as u16, (geometry.y as i32 + dy).max(0) as u16)?;
+                }
+            },
+            NodeCommand::State { state, toggle } => {
+                if let Some(focus) = target.clone() {
+                    let insert = self.config.insert.clone();
+
+                    // the window stays on its own desktop, which isn't necessarily the focused one
+                    let moved = self.holding(&focus, |_, screen, desktop| {
+                        let desktop = &mut screen.desktops[desktop];
+
+                        if desktop.remove(focus.id()) == state && toggle {
+                            desktop.insert(focus.clone(), insert, Point::Any, state.toggle());
+                        } else {
+                            desktop.insert(focus.clone(), insert, Point::Any, state);
+                        }
+                    });
+
+                    if moved.is_some() {
+                        self.set_border(&focus, self.focus.as_ref() == Some(&focus))?;
+                    }
+                }
+            },
+            NodeCommand::Desktop { desktop } => {
+                if let Some((focus, (index, current))) = target.clone().and_then(|focus| self.locate(&focus).map(|at| (focus, at))) {
+                    let overflow = self.config.overflow;
+                    let length = self.screens[index].desktops.len();
+
+                    // the desktop is one of the screen that holds the window
+                    let (desktop, create) = resolve_overflow(desktop, length, overflow)?;
+
+                    if create {
+                        // every screen grows so all of them keep the same number of desktops
+                        self.all(|_, screen| {
+                            screen.resize(screen.desktops.len().max(length + 1));
+
+                            Ok(())
+                        })?;
+
+                        self.sync_desktops();
+
+                        if self.config.ewmh {
+                            self.display
+                                .use_ewmh(&self.root)
+                                .set_number_of_desktops(self.desktop_sizes().iter().sum::<usize>() as u32)?;
+                        }
+
+                        self.update_viewport()?;
+                    }
+
+                    let resolved = (current != desktop).then_some(desktop);
+
+                    if let Some(desktop) = resolved {
+                        let insert = self.config.insert.clone();
+                        let wid = focus.id();
+                        let screen = &mut self.screens[index];
+
+                        let state = screen.desktops[current].remove(wid);
+
+                        screen.desktops[desktop].insert(focus.clone(), insert, Point::Any, state);
+
+                        screen.mark_dirty();
+
+                        let next = screen.desktops[current].focus_target();
+
+                        self.set_border(&focus, false)?;
+
+                        if let Some(next) = focus_after_move(self.focus.as_ref().map(|window| window.id()), wid, next) {
+                            self.focus = None;
+
+                            // keep keyboard control on the desktop the window was moved away from
+                            if let Some(next) = next {
+                                let window = self.display.window_from_id(next)?;
+
+                                self.set_focus(window)?;
+                            }
+                        }
+                    }
+                }
+            },
+            NodeCommand::Ratio { change } => {
+                if let Some(focus) = target.clone() {
+                    let bounds = self.config.bounds;
+                    let level = self.level;
+                    let animate = self.config.animate;
+                    let change_of = Cell::new(None);
+                    let changed = &change_of;
+
+                    let at = self.holding(&focus, |index, screen, desktop| {
+                        // only a desktop that is being looked at is worth animating
+                        let animate = animate.filter(|_| screen.current == desktop);
+
+                        screen.desktops[desktop].map_internal(focus.id(), level, |left, right, split, ratio| {
+                            let next = bounds.clamp(match change {
+                                Change::Add { value } => ratio as i32 + value as i32,
+                                Change::Sub { value } => ratio as i32 - value as i32,
+                                Change::Set { value } => value as i32,
+                            });
+
+                            changed.set(Some((ratio, next)));
+
+                            // an animated change starts out at the old ratio and gets there frame by frame
+                            Node::Internal { left, right, split, ratio: animate.map(|_| ratio).unwrap_or(next) }
+                        });
+
+                        animate.map(|duration| (index, desktop, duration))
+                    });
+
+                    match (at.flatten(), change_of.get()) {
+                        (Some((index, desktop, duration)), Some((from, to))) if from != to => {
+                            self.animate(index, desktop, focus.id(), level, from, to, duration);
+                        },
+                        _ => {},
+                    }
+                }
+            },
+            NodeCommand::Snap { position } => {
+                if let Some(focus) = target.clone() {
+                    let padding = self.config.padding.clone();
+                    let gaps = self.config.gaps.clone();
+                    let border = self.config.border;
+
+                    let area = self.focused(|_, screen| {
+                        Ok(screen.is_floating(&focus).then(|| screen.area.shrink(screen.padding(padding)).snap(position)))
+                    })?;
+
+                    if let Some(area) = area.map(|area| area.inset(gaps as u16)) {
+                        focus.mov_resize(area.x, area.y, area.width.saturating_sub(border.width(true) * 2).max(1), area.height.saturating_sub(border.width(true) * 2).max(1))?;
+                    }
+                }
+            },
+            NodeCommand::Resize { dir, px } => {
+                if let Some(focus) = target.clone() {
+                    let padding = self.config.padding.clone();
+
+                    self.holding(&focus, |_, screen, desktop| {
+                        let padding = screen.padding(padding);
+
+                        screen.desktops[desktop].grow(focus.id(), padding, dir, px);
+                    });
+                }
+            },
+            NodeCommand::Expand { ratio } | NodeCommand::Contract { ratio } => {
+                if let Some(focus) = target.clone() {
+                    let ratio = match node {
+                        NodeCommand::Contract { .. } => ratio.saturating_neg(),
+                        _ => ratio,
+                    };
+
+                    self.holding(&focus, |_, screen, desktop| screen.desktops[desktop].expand(focus.id(), ratio));
+                }
+            },
+            NodeCommand::MoveInContainer { pos } => {
+                if let Some(focus) = target.clone() {
+                    self.holding(&focus, |_, screen, desktop| screen.desktops[desktop].reorder(focus.id(), pos));
+                }
+            },
+            NodeCommand::Equalize => {
+                if let Some(focus) = target.clone() {
+                    let bounds = self.config.bounds;
+                    let level = self.level;
+
+                    self.holding(&focus, |_, screen, desktop| {
+                        screen.desktops[desktop].map_internal(focus.id(), level, |left, right, split, _| {
+                            Node::Internal {
+                                left,
+                                right,
+                                split,
+                                ratio: bounds.clamp(Insert::default().ratio as i32),
+                            }
+                        });
+                    });
+                }
+            },
+            NodeCommand::Reverse =>  {
+                if let Some(focus) = target.clone() {
+                    let level = self.level;
+
+                    self.holding(&focus, |_, screen, desktop| {
+                        screen.desktops[desktop].map_internal(focus.id(), level, |mut left, mut right, split, ratio| {
+                            right.reverse();
+
+                            left.reverse();
+
+                            Node::Internal {
+                                left: right,
+                                right: left,
+                                split,
+                                ratio,
+                            }
+                        });
+                    });
+                }
+            },
+            NodeCommand::Kill { force } => {
+                if let Some(focus) = target.clone() {
+                    let pid = self.windows.get(&focus.id())
+                        .and_then(|client| client.local_pid())
+                        .filter(|_| force);
+
+                    match pid {
+                        Some(pid) => {
+                            let status = process::Command::new("kill")
+                                .args(["-KILL", &pid.to_string()])
+                                .status()?;
+
+                            if !status.success() {
+                                return Err(format!("failed to kill process {pid}, kill exited with {status}").into());
+                            }
+                        },
+                        None => focus.kill()?,
+                    }
+                }
+            },
+            NodeCommand::Close => {
+                if let Some(focus) = target.clone() {
+                    focus.send_event(Event::ClientMessage {
+                        format: 32,
+                        window: focus.id(),
+                        type_: self.atoms.wm_protocols.clone(),
+                        data: ClientMessageData::Long([
+                            self.atoms.wm_delete.id(),
+                            0,
+                            0,
+                            0,
+                            0,
+                        ]),
+                    }, Vec::new(), false)?;
+                }
+            },
+            NodeCommand::Mark { name } => {
+                if let Some(focus) = target.clone() {
+                    self.marks.insert(name, focus.id());
+                }
+            },
+            NodeCommand::Unmark { name } => {
+                self.marks.remove(&name);
+            },
+            NodeCommand::ToggleSplit => {
+                if let Some(focus) = target.clone() {
+                    self.holding(&focus, |_, screen, desktop| {
+                        // the children stay on the same side so only the orientation changes
+                        screen.desktops[desktop].map_internal(focus.id(), 0, |left, right, split, ratio| {
+                            Node::Internal {
+                                left,
+                                right,
+                                split: split.toggle(),
+                                ratio,
+                            }
+                        });
+                    });
+                }
+            },
+            NodeCommand::Duplicate => {
+                if let Some(focus) = target.clone() {
+                    let argv = self.get_command(&focus)?;
+
+                    if argv.is_empty() {
+                        let (_, class) = self.get_class(&focus)?;
+
+                        return Err(format!("{} doesn't say how it was started", class.unwrap_or_else(|| format!("{:#x}", focus.id()))).into());
+                    }
+
+                    spawn("exec \"$@\"", &argv)?;
+                }
+            },
+            NodeCommand::Promote => {
+                if let Some(focus) = target.clone() {
+                    self.holding(&focus, |_, screen, desktop| screen.desktops[desktop].promote(focus.id()));
+                }
+            },
+            NodeCommand::Flatten { dir } => {
+                if let Some(focus) = target.clone() {
+                    let level = self.level;
+                    let target = Split::from(dir);
+
+                    self.holding(&focus, |_, screen, desktop| {
+                        screen.desktops[desktop].map_internal(focus.id(), level, |left, right, split, ratio| {
+                            let windows = [left.collect(), right.collect()].concat();
+
+                            Node::balanced(&windows, target).unwrap_or(Node::Internal { left, right, split, ratio })
+                        });
+                    });
+                }
+            },
+            NodeCommand::Private { toggle } => {
+                if let Some(focus) = target.clone() {
+                    self.all(|_, screen| {
+                        for desktop in screen.desktops.iter_mut() {
+                            desktop.set_private(focus.id(), toggle);
+                        }
+
+                        Ok(())
+                    })?;
+                }
+            },
+            NodeCommand::Parent => {
+                if let Some(focus) = target.clone() {
+                    let depth = self.locate(&focus)
+                        .and_then(|(index, desktop)| self.screens[index].desktops[desktop].clients.as_ref()?.depth(focus.id()))
+                        .unwrap_or_default();
+
+                    self.level = (self.level + 1).min(depth.saturating_sub(1));
+                }
+            },
+            NodeCommand::Child => {
+                self.level = self.level.saturating_sub(1);
+            },
+            // answered directly by `handle_command` since the client wants to know what matched
+            NodeCommand::FocusByTitle { .. } => {},
+            NodeCommand::SaveLayout { name } => {
+                let path = WindowManager::layout_path(&name)?;
+
+                let template = self.focused(|_, screen| Ok(screen.current_mut().and_then(|desktop| desktop.template())))?
+                    .ok_or("the focused desktop has no tiled windows")?;
+
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+
+                fs::write(path, serde_json::to_string_pretty(&template)?)?;
+            },
+            NodeCommand::LoadLayout { name } => {
+                let template: Template = serde_json::from_str(&fs::read_to_string(WindowManager::layout_path(&name)?)?)?;
+
+                self.focused(|_, screen| {
+                    if let Some(desktop) = screen.current_mut() {
+                        desktop.apply_template(template.clone());
+                    }
+
+                    screen.mark_dirty();
+
+                    Ok(())
+                })?;
+            },
+            NodeCommand::ClearReceptacles => self.focused(|_, screen| {
+                if let Some(desktop) = screen.current_mut() {
+                    desktop.clear_receptacles();
+                }
+
+                screen.mark_dirty();
+
+                Ok(())
+            })?,
+            NodeCommand::Grab => self.grabbed = target.map(|window| window.id()),
+            NodeCommand::Drop { dir } => {
+                let grabbed = self.grabbed.take().ok_or("nothing to drop")?;
+
+                if let Some(target) = target.filter(|target| target.id() != grabbed) {
+                    let window = self.display.window_from_id(grabbed)?;
+                    let insert = Insert::new(dir, self.config.insert.ratio);
+
+                    // the window is dropped onto the current desktop of the target, so it has to be visible
+                    if !self.is_managed(&window) || !self.screens.iter().any(|screen| screen.is_visible(&target)) {
+                        return Err("the grabbed window has to be managed and the target visible".into());
+                    }
+
+                    // the screens can differ, both are retiled once the queue drains
+                    self.all(|_, screen| {
+                        if screen.contains(&window) {
+                            screen.forget(grabbed);
+
+                            screen.mark_dirty();
+                        }
+
+                        Ok(())
+                    })?;
+
+                    self.all(|_, screen| {
+                        if screen.is_visible(&target) {
+                            screen.insert(window.clone(), insert, Point::Window(target.clone()), State::Tiled);
+
+                            screen.mark_dirty();
+                        }
+
+                        Ok(())
+                    })?;
+                }
+            },
+            NodeCommand::Center { float } => {
+                if let Some(focus) = target.clone() {
+                    let insert = self.config.insert.clone();
+                    let padding = self.config.padding.clone();
+
+                    let area = self.holding(&focus, |_, screen, desktop| {
+                        let floating = screen.desktops[desktop].is_floating(&focus);
+
+                        if float && !floating {
+                            screen.desktops[desktop].remove(focus.id());
+
+                            screen.desktops[desktop].insert(focus.clone(), insert, Point::Any, State::Float);
+                        }
+
+                        (float || floating).then(|| screen.area.shrink(screen.padding(padding)))
+                    }).flatten();
+
+                    if let Some(area) = area {
+                        self.center_in(&focus, area)?;
+
+                        self.set_border(&focus, self.focus.as_ref() == Some(&focus))?;
+                    }
+                }
+            },
+            NodeCommand::Warp => {
+                if let Some(window) = target.filter(|window| self.screens.iter().any(|screen| screen.is_visible(window))) {
+                    self.warp(&window)?;
+                }
+            },
+            NodeCommand::Cycle { reverse } => {
+                self.focused(|_, screen| {
+                    if let Some(desktop) = screen.current_mut() {
+                        desktop.rotate(reverse);
+                    }
+
+                    screen.mark_dirty();
+
+                    Ok(())
+                })?;
+            },
+            NodeCommand::FocusDirection { dir } => {
+                if let Some(window) = target.map(|target| self.neighbour(&target, dir)).transpose()?.flatten() {
+                    if self.screens.iter().any(|screen| screen.is_floating(&window)) {
+                        window.raise()?;
+                    }
+
+                    self.set_focus(window)?;
+                }
+            },
+            NodeCommand::FocusLayer { layer } => {
+                if let Some(wid) = self.focused(|_, screen| Ok(screen.layer_target(layer)))? {
+                    let window = self.display.window_from_id(wid)?;
+
+                    if layer == Layer::Floating {
+                        window.raise()?;
+                    }
+
+                    self.set_focus(window)?;
+                }
+            },
+            NodeCommand::Pin => {
+                if let Some(focus) = target.clone() {
+                    let padding = self.config.padding.clone();
+                    let gaps = self.config.gaps.clone();
+
+                    self.focused(move |_, screen| {
+                        let padding = screen.padding(padding);
+
+                        if let Some(desktop) = screen.current_mut() {
+                            desktop.pin(focus.id(), padding, gaps);
+                        }
+
+                        Ok(())
+                    })?;
+                }
+            },
+            NodeCommand::Unpin => {
+                if let Some(focus) = target.clone() {
+                    self.focused(move |_, screen| {
+                        if let Some(desktop) = screen.current_mut() {
+                            desktop.unpin(focus.id());
+                        }
+
+                        Ok(())
+                    })?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn handle_config(&mut self, args: Arguments) -> Result<Response, Box<dyn std::error::Error>> {
+        println!("config: {:?}", args);
+
+        if let Some(monitor) = args.target_monitor.filter(|monitor| *monitor >= self.screens.len()) {
+            return Err(format!("monitor {} does not exist", monitor).into());
+        }
+
+        // commands pick their screen through `focused`, which follows the monitor while it is set,
+        // switching desktops is the only command that follows the pointer since the others act on
+        // the focused window
+        self.monitor = match &args.command {
+            Command::Desktop(_) => args.target_monitor.or(self.pointer_screen()),
+            _ => args.target_monitor,
+        };
+
+        let focus = self.focus.as_ref().map(|window| window.id());
+
+        let warp = match &args.command {
+            Command::Node { .. } => self.config.pf.warp_on_focus,
+            Command::Desktop(_) => self.config.pf.warp_on_switch,
+            _ => false,
+        };
+
+        let switch = matches!(args.command, Command::Desktop(_));
+
+        let response = self.handle_command(args.command);
+
+        self.monitor = None;
+
+        if warp && response.is_ok() {
+            self.follow_focus(focus, switch)?;
+        }
+
+        response
+    }
+
+    /// warps the pointer to the focus if a command moved it, switching to a desktop without a
+    /// visible focus moves the pointer to the center of the screen instead
+    fn follow_focus(&mut self, previous: Option<u32>, switch: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let visible = self.focus.as_ref()
+            .map(|focus| self.screens.iter().any(|screen| screen.is_visible(focus)))
+            .unwrap_or(false);
+
+        match self.focus.clone() {
+            Some(focus) if Some(focus.id()) != previous && visible => self.warp(&focus),
+            _ if switch && !visible => {
+                let area = self.focused(|_, screen| Ok(Some(screen.area)))?;
+
+                if let Some(area) = area {
+                    self.root.warp_pointer((area.x + area.width / 2) as i16, (area.y + area.height / 2) as i16)?;
+                }
+
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn handle_command(&mut self, command: Command) -> Result<Response, Box<dyn std::error::Error>> {
+        // TODO: we need to implement node selection, right now we automatically select the focused
+        // node but we want to make it possible for the user to e.g. select the brother node,
+        // parent node and so on.
+        //
+        // this should be a priority before we implement more.
+        //
+        // we will have to implement every selector that bspwm supports.
+        //
+        // we need a function that takes a selector and returns a node if it exists
+        //
+        // TODO: we can represent floating windows as a part of the tree to, how we do this is that
+        // we only only split the area if neither of the leafs are floating
+
+        match command {
+            Command::Node { node: NodeCommand::FocusByTitle { query }, .. } => {
+                let query = query.to_lowercase();
+
+                let window = self.history.iter()
+                    .rev()
+                    .chain(self.windows.keys())
+                    .map(|wid| self.display.window_from_id(*wid))
+                    .collect::<Result<Vec<Window>, _>>()?
+                    .into_iter()
+                    .find(|window| self.title(window).to_lowercase().contains(&query));
+
+                return match window {
+                    Some(window) => {
+                        let wid = window.id();
+
+                        self.summon(window)?;
+
+                        Ok(Response::Message(format!("{:#x}", wid)))
+                    },
+                    None => Err(format!("no window title contains {:?}", query).into()),
+                };
+            },
+            Command::Node { node, selector } if selector.all => {
+                if !matches!(node, NodeCommand::Close | NodeCommand::Kill { .. } | NodeCommand::State { .. }) {
+                    return Err("--all is only supported by close, kill and state".into());
+                }
+
+                // the windows are collected up front since handling them changes the trees
+                for window in self.select_all(&selector)? {
+                    self.handle_node(node.clone(), Some(window))?;
+                }
+            },
+            Command::Node { node, selector } => {
+                let target = self.select(&selector)?;
+
+                self.handle_node(node, target)?;
+            },
+            Command::Desktop(desktop) => match desktop {
+                DesktopCommand::Focus { desktop } => {
+                    let sizes = self.desktop_sizes();
+                    let ewmh = self.config.ewmh.then(|| self.display.use_ewmh(&self.root));
+
+                    let mut target = None;
+                    let mut switched = false;
+
+                    let desktops = self.config.desktops.clone();
+
+                    if self.config.desktops.pinned {
+                        self.focused(|index, screen| {
+                            let current = desktops.resolve(desktop, screen.desktops.len());
+
+                            switched = current != screen.current;
+
+                            screen.current = current;
+
+                            if let Some(ewmh) = &ewmh {
+                                ewmh.set_current_desktop(desktop::global_index(&sizes, index, screen.current) as u32)?;
+                            }
+
+                            target = screen.focus_target();
+
+                            screen.mark_dirty();
+
+                            Ok(())
+                        })?;
+                    } else {
+                        let global = desktops.resolve(desktop, sizes.iter().sum());
+
+                        if let Some((index, local)) = desktop::local_index(&sizes, global) {
+                            if let Some(screen) = self.screens.get_mut(index) {
+                                switched = local != screen.current;
+
+                                screen.current = local;
+
+                                if let Some(ewmh) = &ewmh {
+                                    ewmh.set_current_desktop(global as u32)?;
+                                }
+
+                                target = screen.focus_target();
+
+                                screen.mark_dirty();
+                            }
+                        }
+                    }
+
+                    self.sync_mirror();
+
+                    self.subscribers.emit(format!("desktop {desktop}"))?;
+
+                    self.fire(HookEvent::Desktop, desktop.to_string())?;
+
+                    if let Some(wid) = target {
+                        let window = self.display.window_from_id(wid)?;
+
+                        if self.screens.iter().any(|screen| screen.is_floating(&window)) {
+                            window.raise()?;
+                        }
+
+                        self.focus_shown(window, switched)?;
+                    }
+                },
+                DesktopCommand::Pull { desktop } => {
+                    let sizes = self.desktop_sizes();
+
+                    let source = desktop::local_index(&sizes, desktop)
+                        .ok_or_else(|| format!("no such desktop: {desktop}"))?;
+
+                    let target = self.focused(|index, screen| Ok(Some((index, screen.current))))?;
+
+                    // a desktop that is already visible somewhere stays where it is
+                    if let Some(target) = target.filter(|_| self.screens[source.0].current != source.1) {
+                        self.swap_desktops(source, target);
+
+                        if self.config.ewmh {
+                            self.display
+                                .use_ewmh(&self.root)
+                                .set_current_desktop(desktop::global_index(&sizes, target.0, target.1) as u32)?;
+                        }
+
+                        self.flush_tiles()?;
+
+                        self.subscribers.emit(format!("desktop {}", desktop::global_index(&sizes, target.0, target.1)))?;
+
+                        self.fire(HookEvent::Desktop, desktop::global_index(&sizes, target.0, target.1).to_string())?;
+
+                        if let Some(wid) = self.screens[target.0].focus_target() {
+                            let window = self.display.window_from_id(wid)?;
+
+                            self.set_focus(window)?;
+                        }
+                    }
+                },
+            },
+            Command::Config(config) => match config {
+                ConfigCommand::Desktops { names, pinned, wrap } => {
+                    let length = names.len();
+
+                    self.config.desktops = crate::config::Desktops {
+                        names,
+                        pinned,
+                        wrap,
+                    };
+
+                    self.all(|_, screen| {
+                        screen.resize(length);
+
+                        Ok(())
+                    })?;
+
+                    self.sync_desktops();
+
+                    if self.config.ewmh {
+                        self.display
+                            .use_ewmh(&self.root)
+                            .set_number_of_desktops((length * self.screens.len()) as u32)?;
+                    }
+
+                    self.update_viewport()?;
+                },
+                ConfigCommand::Window { gaps } => {
+                    if gaps != self.config.gaps {
+                        self.config.gaps = gaps;
+
+                        self.retile_tiled();
+                    }
+                },
+                ConfigCommand::DefinePreset { name, gaps, padding, border_width } => {
+                    let padding = padding.map(|padding| Padding {
+                        top: padding[0],
+                        bottom: padding[1],
+                        left: padding[2],
+                        right: padding[3],
+                    });
+
+                    self.config.presets.insert(name, Preset {
+                        gaps,
+                        padding,
+                        border_width,
+                    });
+                },
+                ConfigCommand::Preset { name } => {
+                    let preset = self.config.presets.get(&name)
+                        .cloned()
+                        .ok_or_else(|| format!("no such preset: {name}"))?;
+
+                    self.config.gaps = preset.gaps.unwrap_or(self.config.gaps);
+                    self.config.padding = preset.padding.unwrap_or(self.config.padding);
+                    self.config.border.width = preset.border_width.unwrap_or(self.config.border.width);
+
+                    for window in self.managed_windows() {
+                        self.set_border(&window, self.focus.as_ref() == Some(&window))?;
+                    }
+
+                    self.mark_dirty();
+                },
+                ConfigCommand::GapsChange { change } => {
+                    let gaps = match change {
+                        GapChange::Add { value } => self.config.gaps as i32 + value as i32,
+                        GapChange::Sub { value } => self.config.gaps as i32 - value as i32,
+                        GapChange::Set { value } => value as i32,
+                    }.clamp(0, 200) as u8;
+
+                    if gaps != self.config.gaps {
+                        self.config.gaps = gaps;
+
+                        self.retile_tiled();
+                    }
+                },
+                ConfigCommand::Border { normal, focused, width, floating_normal, floating_focused, floating_width } => {
+                    let previous = self.config.border;
+
+                    self.config.border = crate::config::Border {
+                        normal: u32::from_str_radix(&normal, 16)?,
+                        focused: u32::from_str_radix(&focused, 16)?,
+                        width,
+                        floating_normal: floating_normal.map(|pixel| u32::from_str_radix(&pixel, 16)).transpose()?,
+                        floating_focused: floating_focused.map(|pixel| u32::from_str_radix(&pixel, 16)).transpose()?,
+                        floating_width,
+                        enabled: self.config.border.enabled,
+                        focused_only: self.config.border.focused_only,
+                    };
+
+                    for index in 0..self.screens.len() {
+                        self.refresh_borders(index)?;
+                    }
+
+                    let border = self.config.border;
+
+                    // a change of color alone doesn't move anything
+                    if (border.width(false), border.width(true)) != (previous.width(false), previous.width(true)) {
+                        self.retile_tiled();
+                    }
+                },
+                ConfigCommand::Padding { top, bottom, left, right } => {
+                    let padding = crate::config::Padding {
+                        top,
+                        bottom,
+                        left,
+                        right,
+                    };
+
+                    if padding != self.config.padding {
+                        self.config.padding = padding;
+
+                        self.retile_tiled();
+                    }
+                },
+                ConfigCommand::MonitorPadding { monitor, top, bottom, left, right } => {
+                    let screen = self.screens.get_mut(monitor)
+                        .ok_or_else(|| format!("no such monitor: {monitor}"))?;
+
+                    screen.padding = Some(crate::config::Padding {
+                        top,
+                        bottom,
+                        left,
+                        right,
+                    });
+
+                    screen.mark_dirty();
+                },
+                ConfigCommand::Mirror { source, target } => {
+                    if source == target || source >= self.screens.len() || target >= self.screens.len() {
+                        return Err(format!("invalid mirror from screen {source} to screen {target}").into());
+                    }
+
+                    let template = self.screens[source].current_mut().and_then(|desktop| desktop.template());
+
+                    // the desktop the target ends up on once it follows the source
+                    let current = self.screens[source].current.min(self.screens[target].desktops.len().saturating_sub(1));
+
+                    // checked up front so a template that can't be applied leaves the mirror untouched
+                    if template.is_some() && self.screens[target].desktops.get(current).map(|desktop| desktop.has_tiled()).unwrap_or(false) {
+                        return Err("layout templates can only be applied to an empty desktop".into());
+                    }
+
+                    self.config.mirror = Some((source, target));
+
+                    self.sync_mirror();
+
+                    if let (Some(template), Some(desktop)) = (template, self.screens[target].current_mut()) {
+                        desktop.set_template(template)?;
+                    }
+                },
+                ConfigCommand::Borders { enabled } => {
+                    // the configured widths are kept around so re-enabling restores them
+                    self.config.border.enabled = enabled;
+
+                    for window in self.managed_windows() {
+                        self.set_border(&window, self.focus.as_ref() == Some(&window))?;
+                    }
+
+                    self.mark_dirty();
+                },
+                ConfigCommand::FocusOnMap { enabled } => self.config.focus_on_map = enabled,
+                ConfigCommand::FocusedBorderOnly { enabled } => {
+                    self.config.border.focused_only = enabled;
+
+                    self.mark_dirty();
+
+                    for window in self.managed_windows() {
+                        self.set_border(&window, self.focus.as_ref() == Some(&window))?;
+                    }
+                },
+                ConfigCommand::DefaultState { state } => self.config.default_state = state,
+                ConfigCommand::Swallow { enabled, terminals } => {
+                    self.config.swallow = enabled;
+
+                    if !terminals.is_empty() {
+                        self.config.terminals = terminals;
+                    }
+                },
+                ConfigCommand::Ewmh { enabled } => self.config.ewmh = enabled,
+                ConfigCommand::RatioBounds { min, max } => {
+                    self.config.bounds = RatioBounds::new(min, max)?;
+
+                    self.sync_desktops();
+                },
+                ConfigCommand::Hook { event, command } => match command {
+                    Some(command) => self.config.hooks.push((event, command)),
+                    None => self.config.hooks.retain(|(hook, _)| *hook != event),
+                },
+                ConfigCommand::LazyRestack { enabled } => {
+                    self.config.lazy_restack = enabled;
+
+                    self.sync_desktops();
+                },
+                ConfigCommand::DesktopOverflow { policy } => self.config.overflow = policy,
+                ConfigCommand::Strict { enabled } => self.config.strict = enabled,
+                ConfigCommand::DragToRetile { enabled } => self.config.drag_to_retile = enabled,
+                ConfigCommand::InsertMode { mode } => self.config.insert_mode = mode,
+                ConfigCommand::Layout { mode } => {
+                    self.config.layout = mode;
+
+                    self.sync_desktops();
+                },
+                ConfigCommand::Presel { color } => self.config.presel = u32::from_str_radix(&color, 16)?,
+                ConfigCommand::Mouse { modifier, move_button, resize_button } => {
+                    let mouse = Mouse::new(&modifier, move_button, resize_button)?;
+                    let windows = self.managed_windows();
+
+                    for window in windows.iter() {
+                        self.ungrab_buttons(window)?;
+                    }
+
+                    self.config.mouse = mouse;
+
+                    for window in windows.iter() {
+                        self.grab_buttons(window)?;
+                    }
+                },
+                ConfigCommand::PointerFollowsFocus => {
+                    let enabled = !(self.config.pf.warp_on_focus && self.config.pf.warp_on_switch);
+
+                    self.config.pf.warp_on_focus = enabled;
+                    self.config.pf.warp_on_switch = enabled;
+                },
+                ConfigCommand::PointerWarpOnFocusChange { enabled } => self.config.pf.warp_on_focus = enabled,
+                ConfigCommand::PointerWarpOnSwitch { enabled } => self.config.pf.warp_on_switch = enabled,
+                ConfigCommand::FocusFollowsPointer => self.config.pf.focus_follows ^= true,
+                ConfigCommand::FocusDelay { delay } => self.config.pf.delay = delay,
+                ConfigCommand::FocusWrap { mode } => self.config.focus_wrap = mode,
+                ConfigCommand::HonorFullscreen { enabled } => {
+                    self.config.honor_fullscreen = enabled;
+
+                    // windows that already are fullscreen are put back into their slots
+                    if !enabled {
+                        let windows = self.windows.keys()
+                            .map(|wid| self.display.window_from_id(*wid))
+                            .collect::<Result<Vec<Window>, _>>()?;
+
+                        for window in windows.iter().filter(|window| self.screens.iter().any(|screen| screen.is_fullscreen(window))) {
+                            self.set_fullscreen(window, false)?;
+                        }
+                    }
+                },
+                ConfigCommand::Animate { enabled, duration_ms } => {
+                    self.config.animate = enabled.then_some(duration_ms);
+
+                    if !enabled {
+                        self.animation = None;
+                    }
+                },
+                ConfigCommand::PointerMonitor { enabled } => self.config.pointer_monitor = enabled,
+                ConfigCommand::IgnoreMotifHints => self.config.ignore_motif ^= true,
+            },
+            Command::Query(query) => return self.handle_query(query),
+            Command::Wm(WmCommand::Version) => {
+                return Ok(Response::Message(format!("yokai {} (protocol {})", ipc::VERSION, ipc::PROTOCOL_VERSION)));
+            },
+            Command::Dump { path } => self.dump(path)?,
+            Command::Subscribe { .. } => {},
+            Command::RunOrRaise { class, program } => {
+                let window = self.history.iter()
+                    .rev()
+                    .chain(self.windows.keys())
+                    .find(|wid| self.windows.get(wid).map(|client| client.matches_class(&class)).unwrap_or(false))
+                    .map(|wid| self.display.window_from_id(*wid))
+                    .transpose()?;
+
+                match window {
+                    Some(window) => self.summon(window)?,
+                    None => spawn(&program, &[])?,
+                }
+            },
+            // every command is answered once it has been processed and the queue is in order, so
+            // by the time we get here everything that was queued before has been handled
+            Command::Freeze => self.frozen = true,
+            Command::Thaw if self.frozen => {
+                self.frozen = false;
+
+                // one window failing to map doesn't keep the others from showing up
+                for wid in std::mem::take(&mut self.deferred) {
+                    if let Err(err) = self.map_request(wid) {
+                        eprintln!("yokai: failed to manage {:#x}: {err}", wid);
+                    }
+                }
+
+                self.mark_dirty();
+            },
+            Command::Thaw => {},
+            Command::Sync => {
+                // the reply goes out before the event loop gets to flush, so the barrier has to
+                // apply the pending retiles itself
+                self.flush_tiles()?;
+
+                self.collect_unmaps()?;
+            },
+            Command::Exit => {
+                self.should_close = true;
+            },
+        }
+
+        Ok(Response::Ok)
+    }
+
+    pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let display = self.display.clone();
+        let events = self.events.clone();
+
+        self.load_screens()?;
+
+        self.set_supporting_ewmh()?;
+
+        server::spawn(events.clone(), self.subscribers.clone());
+
+        thread::spawn(move || {
+            if let Err(err) = listen(display, events.clone()) {
+                let _ = events.push(EventType::Shutdown(err.to_string()));
+            }
+        });
+
+        startup::startup()?;
+
+        while !self.should_close {
+            match self.events.wait()? {
+                EventType::XEvent(event) => {
+                    // a single misbehaving window shouldn't take the whole session down with it
+                    if let Err(err) = self.handle_event(event) {
+                        if self.config.strict {
+                            return Err(err);
+                        }
+
+                        eprintln!("yokai: failed to handle event: {err}");
+                    }
+                },
+                EventType::Config(args, reply) => {
+                    let response = self.handle_config(args)
+                        .unwrap_or_else(|err| Response::Error(err.to_string()));
+
+                    // the client may have disconnected without waiting for a response
+                    let _ = reply.send(response);
+                },
+                EventType::Hover(wid) => {
+                    if let Err(err) = self.handle_hover(wid) {
+                        if self.config.strict {
+                            return Err(err);
+                        }
+
+                        eprintln!("yokai: failed to handle event: {err}");
+                    }
+                },
+                EventType::Frame(id) => {
+                    if let Err(err) = self.handle_frame(id) {
+                        if self.config.strict {
+                            return Err(err);
+                        }
+
+                        eprintln!("yokai: failed to handle event: {err}");
+                    }
+                },
+                EventType::Shutdown(reason) => {
+                    eprintln!("yokai: lost the connection to the x server: {reason}");
+
+                    self.should_close = true;
+                },
+            }
+
+            let result = match self.events.is_empty()? {
+                true => self.flush_tiles(),
+                false => Ok(()),
+            };
+
+            if let Err(err) = result.and_then(|_| self.collect_unmaps()) {
+                if self.config.strict {
+                    return Err(err);
+                }
+
+                eprintln!("yokai: failed to update the layout: {err}");
+            }
+        }
+
+        server::cleanup()?;
+
+        Ok(())
+    }
+}
+
+fn listen(display: Display, events: Arc<Queue<EventType>>) -> Result<(), Box<dyn std::error::Error>> {
+    loop {
+        let event = display.next_event()?;
+
+        events.push(EventType::XEvent(event))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configure_keeps_what_the_mask_leaves_out() {
+        let current = Area::new(10, 20, 300, 400);
+
+        assert_eq!(requested_area(0, 1, 2, 3, 4, current), current);
+        assert_eq!(requested_area(CONFIGURE_X | CONFIGURE_HEIGHT, 50, 2, 3, 60, current), Area::new(50, 20, 300, 60));
+        assert_eq!(requested_area(CONFIGURE_X | CONFIGURE_Y, -5, -1, 3, 4, current), Area::new(0, 0, 300, 400));
+    }
+
+    #[test]
+    fn centered_in_an_area() {
+        let area = Area::new(100, 50, 800, 600);
+
+        assert_eq!(centered(area, 200, 100), (400, 300));
+        assert_eq!(centered(area, 800, 600), (100, 50));
+        assert_eq!(centered(area, 801, 601), (100, 50));
+    }
+
+    #[test]
+    fn centered_never_goes_off_the_screen() {
+        assert_eq!(centered(Area::new(0, 0, 100, 100), 300, 500), (0, 0));
+        assert_eq!(centered(Area::new(50, 0, 100, 100), 300, 100), (0, 0));
+    }
+
+    #[test]
+    fn existing_desktops_ignore_the_overflow_policy() {
+        for overflow in [Overflow::Clamp, Overflow::Create, Overflow::Reject] {
+            assert_eq!(resolve_overflow(2, 3, overflow).unwrap(), (2, false));
+        }
+    }
+
+    #[test]
+    fn overflow_clamps_to_the_last_desktop() {
+        assert_eq!(resolve_overflow(3, 3, Overflow::Clamp).unwrap(), (2, false));
+        assert_eq!(resolve_overflow(10, 3, Overflow::Clamp).unwrap(), (2, false));
+    }
+
+    #[test]
+    fn overflow_creates_only_the_next_desktop() {
+        assert_eq!(resolve_overflow(3, 3, Overflow::Create).unwrap(), (3, true));
+        assert!(resolve_overflow(4, 3, Overflow::Create).is_err());
+    }
+
+    #[test]
+    fn overflow_rejects_a_missing_desktop() {
+        assert!(resolve_overflow(3, 3, Overflow::Reject).is_err());
+    }
+
+    #[test]
+    fn closing_the_last_window_releases_the_focus_to_the_root() {
+        let (closed, elsewhere) = (1, 2);
+
+        // the history still holds a window on another screen, which isn't available here
+        assert_eq!(revert_focus(&[elsewhere, closed], |_| false, None), None);
+        assert_eq!(revert_focus(&[], |_| true, None), None);
+    }
+
+    #[test]
+    fn the_focus_reverts_to_the_most_recent_available_window() {
+        assert_eq!(revert_focus(&[1, 2, 3], |wid| wid != 3, Some(4)), Some(2));
+        assert_eq!(revert_focus(&[1, 2, 3], |_| false, Some(4)), Some(4));
+    }
+
+    #[test]
+    fn moving_the_focused_window_away_focuses_the_source_desktop() {
+        let mut source = Node::<u32>::balanced(&[1, 2, 3], Split::Horizontal).unwrap();
+
+        source.remove(1);
+
+        assert_eq!(focus_after_move(Some(1), 1, source.first().copied()), Some(Some(2)));
+    }
+
+    #[test]
+    fn moving_another_window_away_keeps_the_focus() {
+        assert_eq!(focus_after_move(Some(1), 2, Some(3)), None);
+        assert_eq!(focus_after_move(None, 2, Some(3)), None);
+    }
+
+    #[test]
+    fn moving_the_last_window_away_leaves_nothing_focused() {
+        assert_eq!(focus_after_move(Some(1), 1, None), Some(None));
+    }
+
+    #[test]
+    fn focusing_another_window_forgets_the_grab() {
+        let (grabbed, other) = (1, 2);
+
+        assert_eq!(grab_after_focus(Some(grabbed), grabbed), Some(grabbed));
+        assert_eq!(grab_after_focus(Some(grabbed), other), None);
+        assert_eq!(grab_after_focus(None, grabbed), None);
+    }
+
+    #[test]
+    fn configure_only_raises_floating_windows() {
+        assert!(honors_restack(true, CONFIGURE_STACK_MODE, STACK_ABOVE));
+
+        assert!(!honors_restack(false, CONFIGURE_STACK_MODE, STACK_ABOVE));
+        assert!(!honors_restack(true, CONFIGURE_STACK_MODE, 1));
+        assert!(!honors_restack(true, CONFIGURE_WIDTH, STACK_ABOVE));
+    }
+
+    fn padding(top: u16, bottom: u16, left: u16, right: u16) -> Padding {
+        Padding {
+            top,
+            bottom,
+            left,
+            right,
+        }
+    }
+
+    #[test]
+    fn splitting_an_odd_width_loses_no_pixels() {
+        let (left, right) = Area::new(10, 0, 101, 50).split_horizontal(50);
+
+        assert_eq!((left, right), (Area::new(10, 0, 50, 50), Area::new(60, 0, 51, 50)));
+
+        let (top, bottom) = Area::new(0, 10, 50, 101).split_vertical(33);
+
+        assert_eq!((top, bottom), (Area::new(0, 10, 50, 33), Area::new(0, 43, 50, 68)));
+    }
+
+    #[test]
+    fn splitting_clamps_the_ratio_to_the_area() {
+        let area = Area::new(0, 0, 100, 100);
+
+        assert_eq!(area.split_horizontal(-20), (Area::new(0, 0, 0, 100), area));
+        assert_eq!(area.split_vertical(120), (area, Area::new(0, 100, 100, 0)));
+    }
+
+    #[test]
+    fn gaps_larger_than_the_area_leave_nothing() {
+        assert_eq!(Area::new(10, 10, 30, 30).inset(20), Area::new(30, 30, 0, 0));
+        assert_eq!(Area::new(0, 0, 100, 60).inset(10), Area::new(10, 10, 80, 40));
+    }
+
+    #[test]
+    fn shrinking_by_a_padding_that_fits() {
+        let area = Area::new(0, 0, 1920, 1080);
+
+        assert!(area.fits(padding(30, 0, 10, 10)));
+        assert_eq!(area.shrink(padding(30, 0, 10, 10)), Area::new(10, 30, 1900, 1050));
+    }
+
+    #[test]
+    fn shrinking_keeps_the_minimum_tile_size() {
+        let area = Area::new(0, 0, 1000, 800);
+
+        assert!(!area.fits(padding(0, 0, 2000, 0)));
+
+        // the leading padding gives way first, the trailing one is cut off by the minimum
+        assert_eq!(area.shrink(padding(0, 0, 2000, 0)), Area::new(1000 - MIN_TILE_SIZE, 0, MIN_TILE_SIZE, 800));
+        assert_eq!(area.shrink(padding(0, 900, 0, 0)), Area::new(0, 0, 1000, MIN_TILE_SIZE));
+        assert_eq!(area.shrink(padding(100, 2000, 0, 0)), Area::new(0, 100, 1000, MIN_TILE_SIZE));
+    }
+
+    #[test]
+    fn shrinking_an_area_below_the_minimum_tile_size() {
+        let area = Area::new(5, 5, 30, 40);
+
+        assert_eq!(area.shrink(padding(10, 10, 10, 10)), Area::new(5, 5, 30, 40));
+    }
+}